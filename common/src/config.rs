@@ -15,8 +15,19 @@ pub struct Config {
     pub terry: Option<String>,
     /// Endpoint for the CMS contest API. If `None`, CMS integration is disabled.
     pub cms: Option<String>,
-    /// Size in bytes of compilers tarball.
-    pub compilers: HashMap<String, u64>,
+    /// Compiler tarballs, by name.
+    pub compilers: HashMap<String, CompilerInfo>,
+}
+
+/// Information about a compiler tarball.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompilerInfo {
+    /// Size in bytes of the tarball.
+    pub size: u64,
+    /// Version of the tarball, which changes whenever its contents do.
+    pub version: String,
+    /// SHA-256 of the tarball, as lowercase hex.
+    pub sha256: String,
 }
 
 /// Files for newly created workspaces.
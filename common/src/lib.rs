@@ -8,6 +8,8 @@
 //! compilation or language-server lifecycle that drives the IDE.
 #![warn(missing_docs)]
 
+use std::fmt;
+
 use derive_more::From;
 use serde::{Deserialize, Serialize};
 use tracing_subscriber::fmt::format::Pretty;
@@ -70,6 +72,96 @@ pub struct ExecConfig {
     pub mem_limit: Option<u32>,
     /// Optional maximum execution time (in seconds) before the program is forcefully terminated.
     pub time_limit: Option<f64>,
+    /// Optional maximum number of bytes the program may write to its standard output and error.
+    pub output_limit: Option<u64>,
+    /// Run the program deterministically: randomness comes from a PRNG seeded with `seed` and
+    /// the realtime clock is virtual.
+    pub deterministic: bool,
+    /// Seed for deterministic mode, at most [`MAX_SEED`]. If missing, the worker chooses one and
+    /// reports it with [`WorkerExecResponse::Seed`].
+    pub seed: Option<u64>,
+    /// Where the program reads its input from and writes its output to.
+    pub io_mode: IoMode,
+}
+
+/// Largest seed for deterministic mode. Seeds are sent between the frontend and the worker as
+/// JavaScript numbers, which only represent integers exactly up to 2^53 - 1.
+pub const MAX_SEED: u64 = (1 << 53) - 1;
+
+/// How the program's input and output are connected to the frontend.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IoMode {
+    /// The program uses its standard input and output.
+    #[default]
+    Stdio,
+    /// The program reads its input from a file and writes its output to another one. Anything it
+    /// writes on its standard output is reported as standard error. The whole input is read
+    /// before the program starts.
+    Files {
+        /// Path of the input file, relative to the program's working directory.
+        input: String,
+        /// Path of the output file, relative to the program's working directory.
+        output: String,
+    },
+}
+
+impl IoMode {
+    /// Check that the input and output files of [`IoMode::Files`] have valid, distinct names.
+    pub fn validate(&self) -> Result<(), FileNameError> {
+        match self {
+            IoMode::Stdio => Ok(()),
+            IoMode::Files { input, output } => {
+                check_io_file_name(input)?;
+                check_io_file_name(output)?;
+                if input == output {
+                    return Err(FileNameError::SameAsOther);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Check that `name` can be used for the input or output file of [`IoMode::Files`]: it must be a
+/// single path component, other than `.` and `..`.
+pub fn check_io_file_name(name: &str) -> Result<(), FileNameError> {
+    if name.is_empty() {
+        Err(FileNameError::Empty)
+    } else if name.contains(['/', '\0']) || name == "." || name == ".." {
+        Err(FileNameError::NotPlain)
+    } else {
+        Ok(())
+    }
+}
+
+/// Reason why a file name cannot be used in [`IoMode::Files`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileNameError {
+    /// The name is empty.
+    Empty,
+    /// The name contains directories, or is `.` or `..`.
+    NotPlain,
+    /// The input and output files have the same name.
+    SameAsOther,
+}
+
+impl fmt::Display for FileNameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileNameError::Empty => write!(f, "file name is empty"),
+            FileNameError::NotPlain => write!(f, "file name must not contain directories"),
+            FileNameError::SameAsOther => write!(f, "input and output files must be different"),
+        }
+    }
+}
+
+impl std::error::Error for FileNameError {}
+
+impl ExecConfig {
+    /// The seed to run the program with, if deterministic mode is enabled.
+    pub fn deterministic_seed(&self) -> Option<u64> {
+        self.seed.filter(|_| self.deterministic)
+    }
 }
 
 /// Messages emitted by the worker back to the frontend to report on program
@@ -79,6 +171,8 @@ pub enum WorkerExecResponse {
     /// The current status of program execution.
     Status(WorkerExecStatus),
 
+    /// The program runs in deterministic mode with the given seed.
+    Seed(u64),
     /// A chunk of messages produced by the compiler while compiling the program.
     CompilationMessageChunk(Vec<u8>),
     /// A chunk of bytes produced on the program's standard output.
@@ -86,10 +180,96 @@ pub enum WorkerExecResponse {
     /// A chunk of bytes produced on the program's standard error.
     StderrChunk(Vec<u8>),
 
-    /// The program finished execution with an error.
+    /// Compilation or execution could not be carried out.
     Error(String),
+    /// The program ran but did not terminate successfully.
+    Verdict(Verdict),
     /// The program finished execution successfully.
-    Success,
+    Success(ExecStats),
+}
+
+/// Resources used by a program execution.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecStats {
+    /// Wall-clock time (in seconds) the program ran for.
+    pub time: f64,
+    /// Peak memory (in bytes) used by the program, if known.
+    pub memory: Option<u64>,
+    /// Exit code of the program, if it exited on its own.
+    pub exit_code: Option<u32>,
+}
+
+/// How a program that did not terminate successfully ended, as a judge would report it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Verdict {
+    /// The program exited with the given non-zero exit code.
+    NonZeroExit(u32),
+    /// The program was terminated by the given signal (e.g. `SIGABRT` on a failed assertion).
+    Signaled(String),
+    /// The program performed an invalid operation.
+    Trapped(Trap),
+    /// The program ran for longer than the time limit.
+    TimeLimitExceeded,
+    /// The program tried to use more memory than the memory limit.
+    MemoryLimitExceeded,
+    /// The program wrote more output than the output limit.
+    OutputLimitExceeded,
+    /// The execution was stopped by the user.
+    Cancelled,
+}
+
+/// The kind of invalid operation that caused a WebAssembly trap.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Trap {
+    /// An `unreachable` instruction was executed. Compilers emit it after calls that cannot
+    /// return and for undefined behaviour, and wasi-libc's `abort()` is also implemented with it.
+    Unreachable,
+    /// Integer division or remainder by zero.
+    DivisionByZero,
+    /// Integer division overflow (e.g. `INT_MIN / -1`).
+    IntegerOverflow,
+    /// Conversion to an integer of a float that is NaN or out of range.
+    InvalidConversion,
+    /// Access outside of the program's memory.
+    OutOfBounds,
+    /// The call stack was exhausted, usually because of unbounded recursion.
+    StackOverflow,
+    /// Call through a null or mismatched function pointer.
+    BadIndirectCall,
+    /// Any other trap, with the message reported by the browser.
+    Other(String),
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::NonZeroExit(code) => write!(f, "Runtime error: exited with code {code}"),
+            Verdict::Signaled(signal) if signal == "SIGABRT" => {
+                write!(f, "Runtime error: aborted (SIGABRT)")
+            }
+            Verdict::Signaled(signal) => write!(f, "Runtime error: killed by signal {signal}"),
+            Verdict::Trapped(trap) => write!(f, "Runtime error: {trap}"),
+            Verdict::TimeLimitExceeded => write!(f, "Time limit exceeded"),
+            Verdict::MemoryLimitExceeded => write!(f, "Memory limit exceeded"),
+            Verdict::OutputLimitExceeded => write!(f, "Output limit exceeded"),
+            Verdict::Cancelled => write!(f, "Execution cancelled"),
+        }
+    }
+}
+
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trap::Unreachable => write!(f, "unreachable code reached"),
+            Trap::DivisionByZero => write!(f, "integer division by zero"),
+            Trap::IntegerOverflow => write!(f, "integer overflow"),
+            Trap::InvalidConversion => write!(f, "invalid conversion to integer"),
+            Trap::OutOfBounds => write!(f, "out of bounds memory access"),
+            Trap::StackOverflow => write!(f, "stack overflow"),
+            Trap::BadIndirectCall => write!(f, "invalid function pointer call"),
+            Trap::Other(msg) => write!(f, "{msg}"),
+        }
+    }
 }
 
 /// Messages sent from the frontend to the worker to control the language server.
@@ -138,7 +318,7 @@ pub enum WorkerExecStatus {
 }
 
 /// A source code file.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct File {
     /// The file's name.
     pub name: String,
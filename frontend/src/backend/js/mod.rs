@@ -1,6 +1,6 @@
 use std::sync::{Arc, Mutex};
 
-use common::{Language, WorkerExecRequest, WorkerExecResponse, WorkerRequest};
+use common::{Language, Verdict, WorkerExecRequest, WorkerExecResponse, WorkerRequest};
 use gloo_timers::callback::Timeout;
 use send_wrapper::SendWrapper;
 use serde::Serialize;
@@ -108,7 +108,9 @@ impl Backend for JsBackend {
 
                             if matches!(
                                 msg,
-                                WorkerExecResponse::Success | WorkerExecResponse::Error(_)
+                                WorkerExecResponse::Success(_)
+                                    | WorkerExecResponse::Error(_)
+                                    | WorkerExecResponse::Verdict(_)
                             ) {
                                 if let Some(worker) = this.finish_execution() {
                                     worker.terminate();
@@ -146,7 +148,7 @@ impl Backend for JsBackend {
                                 return;
                             };
                             callback(
-                                WorkerExecResponse::Error("Execution timed out".to_string()).into(),
+                                WorkerExecResponse::Verdict(Verdict::TimeLimitExceeded).into(),
                             );
                         }
                     }))
@@ -166,7 +168,7 @@ impl Backend for JsBackend {
                 let Some(callback) = self.callback() else {
                     return;
                 };
-                callback(WorkerExecResponse::Error("Execution cancelled".to_string()).into());
+                callback(WorkerExecResponse::Verdict(Verdict::Cancelled).into());
             }
             WorkerExecRequest::StdinChunk(_) => {
                 unimplemented!("Streaming stdin is not supported in remote backend")
@@ -102,8 +102,10 @@ self.onmessage = function (e) {
             self.postMessage({ StderrChunk: encoder.encode(text) });
         }
 
+        const start = performance.now();
         eval(data.code);
-        self.postMessage({ Success: null });
+        const time = (performance.now() - start) / 1000;
+        self.postMessage({ Success: { time, memory: null, exit_code: 0 } });
     } catch (err) {
         self.postMessage({ Error: err.toString() });
     }
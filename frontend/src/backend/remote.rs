@@ -1,7 +1,7 @@
 use std::sync::{Arc, Mutex};
 
 use anyhow::Result;
-use common::{WorkerExecRequest, WorkerExecResponse, WorkerRequest};
+use common::{ExecStats, Verdict, WorkerExecRequest, WorkerExecResponse, WorkerRequest};
 use futures_channel::oneshot::{self, Sender};
 use futures_util::{FutureExt, select};
 use leptos::task::spawn_local;
@@ -106,21 +106,44 @@ impl Backend for RemoteBackend {
                                     return;
                                 }
                             }
+                            let mut stats = ExecStats {
+                                time: 0.,
+                                memory: None,
+                                exit_code: None,
+                            };
                             if let Some(execution) = res.execution {
                                 callback(WorkerExecResponse::StdoutChunk(execution.stdout.into()).into());
                                 callback(WorkerExecResponse::StderrChunk(execution.stderr.into()).into());
-                                if execution.status != "Success" {
-                                    callback(
-                                        WorkerExecResponse::Error(format!(
-                                            "Execution failed: {}",
-                                            execution.status
-                                        ))
-                                        .into(),
-                                    );
+                                let verdict = match execution.status.as_str() {
+                                    "Success" => None,
+                                    "TimeLimitExceeded" => Some(Verdict::TimeLimitExceeded),
+                                    "MemoryLimitExceeded" => Some(Verdict::MemoryLimitExceeded),
+                                    _ if execution.exit_code != 0 => {
+                                        Some(Verdict::NonZeroExit(execution.exit_code))
+                                    }
+                                    _ => {
+                                        callback(
+                                            WorkerExecResponse::Error(format!(
+                                                "Execution failed: {}",
+                                                execution.status
+                                            ))
+                                            .into(),
+                                        );
+                                        return;
+                                    }
+                                };
+                                if let Some(verdict) = verdict {
+                                    callback(WorkerExecResponse::Verdict(verdict).into());
                                     return;
                                 }
+                                stats = ExecStats {
+                                    time: execution.time,
+                                    // Reported in MiB, like `memory_limit`.
+                                    memory: Some((execution.memory * 1024. * 1024.) as u64),
+                                    exit_code: Some(execution.exit_code),
+                                };
                             }
-                            callback(WorkerExecResponse::Success.into());
+                            callback(WorkerExecResponse::Success(stats).into());
                         }
                         _ = receiver => {
                             let callback = self.callback.lock().unwrap();
@@ -128,7 +151,7 @@ impl Backend for RemoteBackend {
                                 tracing::error!("No callback set for RemoteBackend");
                                 return;
                             };
-                            callback(WorkerExecResponse::Error("Execution cancelled by the user".to_string()).into());
+                            callback(WorkerExecResponse::Verdict(Verdict::Cancelled).into());
                         }
                     }
                 });
@@ -184,7 +207,6 @@ mod api {
     }
 
     #[derive(Debug, Deserialize)]
-    #[allow(unused)]
     pub struct ExecutionResult {
         pub status: String,
         pub exit_code: u32,
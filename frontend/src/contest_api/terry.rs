@@ -143,13 +143,18 @@ impl ContestAPI for Terry {
         while let Some(msg) = receiver.next().await {
             match msg {
                 WorkerExecResponse::StdoutChunk(chunk) => output.extend_from_slice(&chunk),
-                WorkerExecResponse::Success => break,
+                WorkerExecResponse::Success(_) => break,
                 WorkerExecResponse::Error(err) => {
                     message = Some(err);
                     // Even if the execution fails, we still want to submit whatever output it produced
                     break;
                 }
+                WorkerExecResponse::Verdict(verdict) => {
+                    message = Some(verdict.to_string());
+                    break;
+                }
                 WorkerExecResponse::Status(_)
+                | WorkerExecResponse::Seed(_)
                 | WorkerExecResponse::CompilationMessageChunk(_)
                 | WorkerExecResponse::StderrChunk(_) => {}
             }
@@ -8,8 +8,9 @@ use std::sync::Arc;
 use anyhow::{Context, Result};
 use common::config::Config;
 use common::{
-    ExecConfig, File, WorkerExecRequest, WorkerExecResponse, WorkerExecStatus, WorkerLSRequest,
-    WorkerLSResponse, WorkerRequest, WorkerResponse, init_logging,
+    ExecConfig, ExecStats, File, IoMode, Verdict, WorkerExecRequest, WorkerExecResponse,
+    WorkerExecStatus, WorkerLSRequest, WorkerLSResponse, WorkerRequest, WorkerResponse,
+    init_logging,
 };
 use futures_channel::mpsc::{UnboundedSender, unbounded};
 use gloo_net::http::Request;
@@ -46,6 +47,8 @@ pub struct Outcome {
     pub stdout: Vec<u8>,
     pub compile_stderr: Vec<u8>,
     pub stderr: Vec<u8>,
+    /// Seed of the execution, if it ran in deterministic mode.
+    pub seed: Option<u64>,
 }
 
 type FetchingCompilerProgress = HashMap<String, u64>;
@@ -68,6 +71,8 @@ enum StateExec {
     Complete {
         outcome: Outcome,
         error: Option<String>,
+        verdict: Option<Verdict>,
+        stats: Option<ExecStats>,
     },
 }
 
@@ -148,6 +153,10 @@ fn handle_exec_message(msg: WorkerExecResponse, state: RwSignal<RunState>) -> Re
             *status = Some(new);
         }
 
+        (WorkerExecResponse::Seed(seed), StateExec::Processing { outcome, .. }) => {
+            outcome.seed = Some(seed);
+        }
+
         (
             WorkerExecResponse::CompilationMessageChunk(chunk),
             StateExec::Processing { outcome, .. },
@@ -161,10 +170,12 @@ fn handle_exec_message(msg: WorkerExecResponse, state: RwSignal<RunState>) -> Re
             outcome.stderr.extend_from_slice(&chunk);
         }
 
-        (WorkerExecResponse::Success, StateExec::Processing { outcome, .. }) => {
+        (WorkerExecResponse::Success(stats), StateExec::Processing { outcome, .. }) => {
             state.exec = StateExec::Complete {
                 outcome: std::mem::take(outcome),
                 error: None,
+                verdict: None,
+                stats: Some(stats),
             };
         }
 
@@ -172,6 +183,17 @@ fn handle_exec_message(msg: WorkerExecResponse, state: RwSignal<RunState>) -> Re
             state.exec = StateExec::Complete {
                 outcome: std::mem::take(outcome),
                 error: Some(s),
+                verdict: None,
+                stats: None,
+            };
+        }
+
+        (WorkerExecResponse::Verdict(v), StateExec::Processing { outcome, .. }) => {
+            state.exec = StateExec::Complete {
+                outcome: std::mem::take(outcome),
+                error: None,
+                verdict: Some(v),
+                stats: None,
             };
         }
 
@@ -273,6 +295,12 @@ fn App() -> impl IntoView {
         input_mode,
         mem_limit,
         time_limit,
+        output_limit,
+        deterministic,
+        seed,
+        file_io,
+        input_file,
+        output_file,
         ..
     } = use_settings();
 
@@ -433,6 +461,16 @@ fn App() -> impl IntoView {
                     config: ExecConfig {
                         mem_limit: mem_limit.get_untracked().map(|x| x * 16),
                         time_limit: time_limit.get_untracked(),
+                        output_limit: output_limit.get_untracked().map(|x| x as u64 * 1024 * 1024),
+                        deterministic: deterministic.get_untracked(),
+                        seed: seed.get_untracked(),
+                        io_mode: match file_io.get_untracked() {
+                            true => IoMode::Files {
+                                input: input_file.get_untracked(),
+                                output: output_file.get_untracked(),
+                            },
+                            false => IoMode::Stdio,
+                        },
                     },
                 }
                 .into(),
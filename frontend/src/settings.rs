@@ -1,5 +1,6 @@
 use std::num::IntErrorKind;
 
+use common::{FileNameError, MAX_SEED, check_io_file_name};
 use leptos::ev::keydown;
 use leptos::prelude::*;
 use leptos::reactive::wrappers::write::SignalSetter;
@@ -56,7 +57,7 @@ impl DisplayLocalized for InputMode {
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 struct StoredSettings {
     theme: Option<Theme>,
     keyboard_mode: KeyboardMode,
@@ -65,6 +66,24 @@ struct StoredSettings {
     editor_width_percent: f32,
     mem_limit: Option<u32>,
     time_limit: Option<f64>,
+    output_limit: Option<u32>,
+    #[serde(default)]
+    deterministic: bool,
+    seed: Option<u64>,
+    #[serde(default)]
+    file_io: bool,
+    #[serde(default = "default_input_file")]
+    input_file: String,
+    #[serde(default = "default_output_file")]
+    output_file: String,
+}
+
+fn default_input_file() -> String {
+    "input.txt".to_string()
+}
+
+fn default_output_file() -> String {
+    "output.txt".to_string()
 }
 
 impl Default for StoredSettings {
@@ -77,6 +96,12 @@ impl Default for StoredSettings {
             editor_width_percent: 65.0,
             mem_limit: None,
             time_limit: None,
+            output_limit: None,
+            deterministic: false,
+            seed: None,
+            file_io: false,
+            input_file: default_input_file(),
+            output_file: default_output_file(),
         }
     }
 }
@@ -92,6 +117,12 @@ pub struct SettingsProvider {
     pub persist_storage: Signal<bool>,
     pub mem_limit: Signal<Option<u32>>,
     pub time_limit: Signal<Option<f64>>,
+    pub output_limit: Signal<Option<u32>>,
+    pub deterministic: Signal<bool>,
+    pub seed: Signal<Option<u64>>,
+    pub file_io: Signal<bool>,
+    pub input_file: Signal<String>,
+    pub output_file: Signal<String>,
 }
 
 impl SettingsProvider {
@@ -128,6 +159,13 @@ impl SettingsProvider {
             persist_storage: Memo::new(move |_| read.get().persist_storage).into(),
             mem_limit: Memo::new(move |_| read.get().mem_limit).into(),
             time_limit: Memo::new(move |_| read.get().time_limit).into(),
+            output_limit: Memo::new(move |_| read.get().output_limit).into(),
+            deterministic: Memo::new(move |_| read.get().deterministic).into(),
+            // Older versions stored seeds that are too large to send to the worker.
+            seed: Memo::new(move |_| read.get().seed.filter(|&seed| seed <= MAX_SEED)).into(),
+            file_io: Memo::new(move |_| read.get().file_io).into(),
+            input_file: Memo::new(move |_| read.get().input_file).into(),
+            output_file: Memo::new(move |_| read.get().output_file).into(),
         });
     }
 }
@@ -215,6 +253,12 @@ pub fn Settings() -> impl IntoView {
                     <hr />
                     <TimeLimit />
                     <MemLimit />
+                    <OutputLimit />
+                    <DeterministicControl />
+                    <Seed />
+                    <FileIoControl />
+                    <IoFileName output=false />
+                    <IoFileName output=true />
                     <p class="has-text-centered has-text-grey is-size-7 mt-5">
                         {concat!("wasm-ide ", env!("WASM_IDE_VERSION"))}
                     </p>
@@ -506,3 +550,321 @@ fn TimeLimit() -> impl IntoView {
         </div>
     }
 }
+
+#[component]
+fn OutputLimit() -> impl IntoView {
+    let i18n = use_i18n();
+    let settings = use_settings();
+
+    #[derive(Debug, Clone, Copy)]
+    enum Error {
+        NotANumber,
+        NotPositive,
+    }
+
+    impl Error {
+        fn localized_message(&self, loc: Locale) -> String {
+            match self {
+                Error::NotANumber => td_string!(loc, please_enter_a_valid_number).into(),
+                Error::NotPositive => td_string!(loc, value_must_be_positive).into(),
+            }
+        }
+    }
+
+    let error = RwSignal::new(None);
+    let input_ref = NodeRef::<leptos::html::Input>::new();
+
+    let on_input = move |_| {
+        let input = input_ref.get().unwrap();
+        let value = input.value();
+        let value = value.trim();
+
+        match value.parse() {
+            Ok(0) => {
+                error.set(Some(Error::NotPositive));
+            }
+            Ok(v) => {
+                settings.write.update(|s| s.output_limit = Some(v));
+                error.set(None);
+            }
+            Err(e) => match e.kind() {
+                IntErrorKind::Empty => {
+                    settings.write.update(|s| s.output_limit = None);
+                    error.set(None);
+                }
+                _ => {
+                    error.set(Some(Error::NotANumber));
+                }
+            },
+        };
+    };
+
+    view! {
+        <div class:field class:is-horizontal>
+            <div class:field-label class:is-normal>
+                <label class="label">{t!(i18n, output_limit)}</label>
+            </div>
+            <div class="field-body">
+                <div class="field has-addons">
+                    <div class="control">
+                        <input
+                            class:input
+                            class:is-danger=move || error.get().is_some()
+                            on:input=on_input
+                            type="text"
+                            node_ref=input_ref
+                            value=settings
+                                .output_limit
+                                .get_untracked()
+                                .map_or("".to_string(), |v| v.to_string())
+                        />
+                        <ShowLet some=error let:value>
+                            <p class:help class:is-danger>
+                                {move || value.localized_message(i18n.get_locale())}
+                            </p>
+                        </ShowLet>
+                    </div>
+                    <div class="control">
+                        <a class="button is-static">MiB</a>
+                    </div>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn DeterministicControl() -> impl IntoView {
+    let i18n = use_i18n();
+    let settings = use_settings();
+
+    let toggle = move |_| {
+        settings
+            .write
+            .update(|v| v.deterministic = !v.deterministic);
+    };
+
+    view! {
+        <div class:field class:is-horizontal>
+            <div class:field-label class:is-normal>
+                <label class="label">{t!(i18n, deterministic_mode)}</label>
+            </div>
+            <div class="field-body">
+                <div class="control">
+                    <button
+                        class="button"
+                        class:is-info=move || settings.deterministic.get()
+                        on:click=toggle
+                        type="button"
+                    >
+                        {move || {
+                            if settings.deterministic.get() {
+                                t_string!(i18n, enabled).to_string()
+                            } else {
+                                t_string!(i18n, disabled).to_string()
+                            }
+                        }}
+                    </button>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn Seed() -> impl IntoView {
+    let i18n = use_i18n();
+    let settings = use_settings();
+
+    #[derive(Debug, Clone, Copy)]
+    enum Error {
+        NotANumber,
+        TooLarge,
+    }
+
+    impl Error {
+        fn localized_message(&self, loc: Locale) -> String {
+            match self {
+                Error::NotANumber => td_string!(loc, please_enter_a_valid_number).into(),
+                Error::TooLarge => td_string!(loc, value_must_be_at_most_max_seed).into(),
+            }
+        }
+    }
+
+    let error = RwSignal::new(None);
+    let input_ref = NodeRef::<leptos::html::Input>::new();
+
+    let on_input = move |_| {
+        let input = input_ref.get().unwrap();
+        let value = input.value();
+        let value = value.trim();
+
+        match value.parse() {
+            _ if value.is_empty() => {
+                settings.write.update(|s| s.seed = None);
+                error.set(None);
+            }
+            Ok(v) if v > MAX_SEED => {
+                error.set(Some(Error::TooLarge));
+            }
+            Ok(v) => {
+                settings.write.update(|s| s.seed = Some(v));
+                error.set(None);
+            }
+            Err(e) if *e.kind() == IntErrorKind::PosOverflow => {
+                error.set(Some(Error::TooLarge));
+            }
+            Err(_) => {
+                error.set(Some(Error::NotANumber));
+            }
+        };
+    };
+
+    view! {
+        <div class:field class:is-horizontal>
+            <div class:field-label class:is-normal>
+                <label class="label">{t!(i18n, seed)}</label>
+            </div>
+            <div class="field-body">
+                <div class="control">
+                    <input
+                        class:input
+                        class:is-danger=move || error.get().is_some()
+                        on:input=on_input
+                        type="text"
+                        node_ref=input_ref
+                        disabled=move || !settings.deterministic.get()
+                        placeholder=move || t_string!(i18n, random_seed)
+                        value=settings
+                            .seed
+                            .get_untracked()
+                            .map_or("".to_string(), |v| v.to_string())
+                    />
+                    <ShowLet some=error let:value>
+                        <p class:help class:is-danger>
+                            {move || value.localized_message(i18n.get_locale())}
+                        </p>
+                    </ShowLet>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn FileIoControl() -> impl IntoView {
+    let i18n = use_i18n();
+    let settings = use_settings();
+
+    let toggle = move |_| {
+        settings.write.update(|v| v.file_io = !v.file_io);
+    };
+
+    view! {
+        <div class:field class:is-horizontal>
+            <div class:field-label class:is-normal>
+                <label class="label">{t!(i18n, file_io)}</label>
+            </div>
+            <div class="field-body">
+                <div class="control">
+                    <button
+                        class="button"
+                        class:is-info=move || settings.file_io.get()
+                        on:click=toggle
+                        type="button"
+                    >
+                        {move || {
+                            if settings.file_io.get() {
+                                t_string!(i18n, enabled).to_string()
+                            } else {
+                                t_string!(i18n, disabled).to_string()
+                            }
+                        }}
+                    </button>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+/// Name of the input file, or of the output file if `output` is set, used in file I/O mode.
+#[component]
+fn IoFileName(output: bool) -> impl IntoView {
+    let i18n = use_i18n();
+    let settings = use_settings();
+
+    fn localized_message(error: &FileNameError, loc: Locale) -> String {
+        match error {
+            FileNameError::Empty => td_string!(loc, please_enter_a_file_name).into(),
+            FileNameError::NotPlain => {
+                td_string!(loc, file_name_must_not_contain_directories).into()
+            }
+            FileNameError::SameAsOther => {
+                td_string!(loc, input_and_output_files_must_differ).into()
+            }
+        }
+    }
+
+    let error = RwSignal::new(None);
+    let input_ref = NodeRef::<leptos::html::Input>::new();
+
+    let on_input = move |_| {
+        let input = input_ref.get().unwrap();
+        let value = input.value();
+        let value = value.trim();
+
+        let other = match output {
+            true => settings.input_file.get_untracked(),
+            false => settings.output_file.get_untracked(),
+        };
+        let res = check_io_file_name(value).and_then(|()| match value == other {
+            true => Err(FileNameError::SameAsOther),
+            false => Ok(()),
+        });
+        if let Err(e) = res {
+            error.set(Some(e));
+            return;
+        }
+        settings.write.update(|s| match output {
+            true => s.output_file = value.to_string(),
+            false => s.input_file = value.to_string(),
+        });
+        error.set(None);
+    };
+
+    let file_name = match output {
+        true => settings.output_file,
+        false => settings.input_file,
+    };
+
+    view! {
+        <div class:field class:is-horizontal>
+            <div class:field-label class:is-normal>
+                <label class="label">
+                    {move || match output {
+                        true => t_string!(i18n, output_file),
+                        false => t_string!(i18n, input_file),
+                    }}
+                </label>
+            </div>
+            <div class="field-body">
+                <div class="control">
+                    <input
+                        class:input
+                        class:is-danger=move || error.get().is_some()
+                        on:input=on_input
+                        type="text"
+                        node_ref=input_ref
+                        disabled=move || !settings.file_io.get()
+                        value=file_name.get_untracked()
+                    />
+                    <ShowLet some=error let:value>
+                        <p class:help class:is-danger>
+                            {move || localized_message(&value, i18n.get_locale())}
+                        </p>
+                    </ShowLet>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -1,6 +1,6 @@
-use common::WorkerExecStatus;
 use common::config::Config;
-use leptos::either::{Either, EitherOf3, EitherOf4, EitherOf5};
+use common::{ExecStats, Trap, Verdict, WorkerExecStatus};
+use leptos::either::{Either, EitherOf3, EitherOf4, EitherOf7};
 use leptos::prelude::*;
 use tracing::warn;
 
@@ -44,36 +44,42 @@ pub fn StatusView(
     let i18n = use_i18n();
 
     let render_exec = move |exec: &StateExec| match exec {
-        StateExec::Ready | StateExec::Complete { error: None, .. } => None,
+        StateExec::Ready
+        | StateExec::Complete {
+            error: None,
+            verdict: None,
+            stats: None,
+            ..
+        } => None,
 
-        StateExec::Processing { stopping: true, .. } => Some(EitherOf5::A(
+        StateExec::Processing { stopping: true, .. } => Some(EitherOf7::A(
             view! { <Message kind="is-warning">{t!(i18n, stopping_execution)}</Message> },
         )),
 
         StateExec::Processing {
             status: Some(WorkerExecStatus::FetchingCompiler),
             ..
-        } => Some(EitherOf5::B(
+        } => Some(EitherOf7::B(
             view! { <FetchingCompilerMessageBar fetching_compiler_progress /> },
         )),
 
         StateExec::Processing {
             status: Some(WorkerExecStatus::Compiling),
             ..
-        } => Some(EitherOf5::C(
+        } => Some(EitherOf7::C(
             view! { <Message kind="is-success">{t!(i18n, compiling)}</Message> },
         )),
 
         StateExec::Processing {
             status: None | Some(WorkerExecStatus::Running),
             ..
-        } => Some(EitherOf5::D(
+        } => Some(EitherOf7::D(
             view! { <Message kind="is-success">{t!(i18n, executing)}</Message> },
         )),
 
         StateExec::Complete {
             error: Some(err), ..
-        } => Some(EitherOf5::E(view! {
+        } => Some(EitherOf7::E(view! {
             <ErrorMessageBar
                 err
                 clear=move || {
@@ -86,6 +92,44 @@ pub fn StatusView(
                 }
             />
         })),
+
+        StateExec::Complete {
+            verdict: Some(verdict),
+            outcome,
+            ..
+        } => Some(EitherOf7::F(view! {
+            <VerdictMessageBar
+                verdict=verdict.clone()
+                seed=outcome.seed
+                clear=move || {
+                    match &mut state.write().exec {
+                        StateExec::Complete { verdict, .. } => {
+                            *verdict = None;
+                        }
+                        _ => warn!("Unexpected state when hiding verdict"),
+                    }
+                }
+            />
+        })),
+
+        StateExec::Complete {
+            stats: Some(stats),
+            outcome,
+            ..
+        } => Some(EitherOf7::G(view! {
+            <StatsMessageBar
+                stats=stats.clone()
+                seed=outcome.seed
+                clear=move || {
+                    match &mut state.write().exec {
+                        StateExec::Complete { stats, .. } => {
+                            *stats = None;
+                        }
+                        _ => warn!("Unexpected state when hiding execution stats"),
+                    }
+                }
+            />
+        })),
     };
 
     let render_ls = move |ls: &StateLS| match ls {
@@ -188,6 +232,132 @@ fn ErrorMessageBar(
     }
 }
 
+#[component]
+fn VerdictMessageBar(
+    verdict: Verdict,
+    seed: Option<u64>,
+    clear: impl Fn() + Send + Sync + 'static + Clone,
+) -> impl IntoView {
+    let i18n = use_i18n();
+    let title = move || t_string!(i18n, hide_error);
+    let clear = move |_| clear();
+    let kind = match verdict {
+        Verdict::Cancelled => "is-warning",
+        _ => "is-danger",
+    };
+    let header = match &verdict {
+        Verdict::NonZeroExit(_) | Verdict::Signaled(_) | Verdict::Trapped(_) => {
+            t!(i18n, runtime_error).into_any()
+        }
+        Verdict::TimeLimitExceeded => t!(i18n, time_limit_exceeded).into_any(),
+        Verdict::MemoryLimitExceeded => t!(i18n, memory_limit_exceeded).into_any(),
+        Verdict::OutputLimitExceeded => t!(i18n, output_limit_exceeded).into_any(),
+        Verdict::Cancelled => t!(i18n, execution_cancelled).into_any(),
+    };
+    let details = match verdict {
+        Verdict::NonZeroExit(code) => t!(i18n, exited_with_code, code).into_any(),
+        Verdict::Signaled(signal) if signal == "SIGABRT" => t!(i18n, aborted).into_any(),
+        Verdict::Signaled(signal) => {
+            t!(i18n, killed_by_signal, signal = move || signal.clone()).into_any()
+        }
+        Verdict::Trapped(trap) => match trap {
+            Trap::Unreachable => t!(i18n, trap_unreachable).into_any(),
+            Trap::DivisionByZero => t!(i18n, trap_division_by_zero).into_any(),
+            Trap::IntegerOverflow => t!(i18n, trap_integer_overflow).into_any(),
+            Trap::InvalidConversion => t!(i18n, trap_invalid_conversion).into_any(),
+            Trap::OutOfBounds => t!(i18n, trap_out_of_bounds).into_any(),
+            Trap::StackOverflow => t!(i18n, trap_stack_overflow).into_any(),
+            Trap::BadIndirectCall => t!(i18n, trap_bad_indirect_call).into_any(),
+            Trap::Other(msg) => msg.into_any(),
+        },
+        Verdict::TimeLimitExceeded => t!(i18n, time_limit_exceeded_details).into_any(),
+        Verdict::MemoryLimitExceeded => t!(i18n, memory_limit_exceeded_details).into_any(),
+        Verdict::OutputLimitExceeded => t!(i18n, output_limit_exceeded_details).into_any(),
+        Verdict::Cancelled => t!(i18n, execution_cancelled_details).into_any(),
+    };
+    view! {
+        <Message kind=kind>
+            <MessageHeader slot>
+                <p>{header}</p>
+                <button
+                    class="delete"
+                    aria-label="delete"
+                    title=title
+                    on:click=clear.clone()
+                ></button>
+            </MessageHeader>
+            <p>{details}</p>
+            {seed.map(|seed| view! { <p>{t!(i18n, execution_seed, seed)}</p> })}
+        </Message>
+    }
+}
+
+#[component]
+fn StatsMessageBar(
+    stats: ExecStats,
+    seed: Option<u64>,
+    clear: impl Fn() + Send + Sync + 'static + Clone,
+) -> impl IntoView {
+    let i18n = use_i18n();
+    let title = move || t_string!(i18n, hide_stats);
+    let clear = move |_| clear();
+    view! {
+        <Message kind="is-success">
+            <MessageHeader slot>
+                <p>{t!(i18n, execution_completed)}</p>
+                <button
+                    class="delete"
+                    aria-label="delete"
+                    title=title
+                    on:click=clear.clone()
+                ></button>
+            </MessageHeader>
+            <table
+                class:table
+                style:--bulma-table-background-color="none"
+                style:--bulma-table-color="inherit"
+                style:--bulma-table-cell-border-width="0"
+            >
+                <tbody>
+                    <tr>
+                        <th>{t!(i18n, execution_time)}</th>
+                        <td>{format!("{:.3} s", stats.time)}</td>
+                    </tr>
+                    {stats
+                        .memory
+                        .map(|memory| {
+                            view! {
+                                <tr>
+                                    <th>{t!(i18n, peak_memory)}</th>
+                                    <td>{format!("{:.1} MiB", memory as f64 / (1 << 20) as f64)}</td>
+                                </tr>
+                            }
+                        })}
+                    {stats
+                        .exit_code
+                        .map(|exit_code| {
+                            view! {
+                                <tr>
+                                    <th>{t!(i18n, exit_code)}</th>
+                                    <td>{exit_code}</td>
+                                </tr>
+                            }
+                        })}
+                    {seed
+                        .map(|seed| {
+                            view! {
+                                <tr>
+                                    <th>{t!(i18n, seed)}</th>
+                                    <td>{seed}</td>
+                                </tr>
+                            }
+                        })}
+                </tbody>
+            </table>
+        </Message>
+    }
+}
+
 #[component]
 fn FetchingCompilerMessageBar(
     fetching_compiler_progress: RwSignal<FetchingCompilerProgress>,
@@ -200,7 +370,7 @@ fn FetchingCompilerMessageBar(
             let name = name.clone();
             Signal::derive(move || fetching_compiler_progress.read().get(&name).cloned())
         };
-        let max = config.compilers.get(&name).copied();
+        let max = config.compilers.get(&name).map(|info| info.size);
         view! {
             <tr>
                 <td class:is-family-monospace>{name}</td>
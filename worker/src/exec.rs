@@ -1,6 +1,7 @@
 use std::cell::RefCell;
 
-use common::{ExecConfig, File, WorkerExecRequest, WorkerExecResponse};
+use common::{ExecConfig, File, MAX_SEED, Verdict, WorkerExecRequest, WorkerExecResponse};
+use futures::channel::mpsc::UnboundedSender;
 use futures::channel::oneshot::{Sender, channel};
 use futures::{FutureExt, select};
 use tracing::{info, warn};
@@ -13,27 +14,43 @@ use crate::{lang, send_msg, util};
 pub struct WorkerStateExec {
     stop: RefCell<Option<Sender<()>>>,
     stdin: RefCell<Option<Pipe>>,
+    stdin_feeder: RefCell<Option<UnboundedSender<Vec<u8>>>>,
 }
 
 fn state() -> &'static WorkerStateExec {
     &crate::state().exec
 }
 
+/// Chooses a seed for deterministic mode when the frontend does not provide one.
+pub fn random_seed() -> u64 {
+    (js_sys::Math::random() * (MAX_SEED + 1) as f64) as u64
+}
+
 fn run(
     files: Vec<File>,
     primary_file: String,
     language: String,
     input: Option<Vec<u8>>,
-    config: ExecConfig,
+    mut config: ExecConfig,
 ) {
     info!("Starting execution of {:?} code", language);
 
+    if config.deterministic {
+        let seed = *config.seed.get_or_insert_with(random_seed);
+        send_msg(WorkerExecResponse::Seed(seed));
+    }
+
     let (sender, mut receiver) = channel();
     state().stop.borrow_mut().replace(sender);
     let stdin = Pipe::new();
+    let stdin_feeder = stdin.feeder();
     if let Some(input) = input {
-        stdin.write(&input);
-        stdin.close();
+        // Dropping the feeder closes stdin once the input is written. Chunks must not go to the
+        // feeder of a previous run.
+        let _ = stdin_feeder.unbounded_send(input);
+        state().stdin_feeder.borrow_mut().take();
+    } else {
+        state().stdin_feeder.borrow_mut().replace(stdin_feeder);
     }
     state().stdin.borrow_mut().replace(stdin.clone());
     let stdout = Pipe::new();
@@ -45,12 +62,15 @@ fn run(
             select! {
                 _ = receiver => {
                     info!("Received stop command, cancelling execution");
-                    send_msg(WorkerExecResponse::Error("Execution cancelled by user".to_string()));
+                    send_msg(WorkerExecResponse::Verdict(Verdict::Cancelled));
                 }
                 res = running.fuse() => {
                     info!("Execution finished");
                     match res {
-                        Ok(()) => send_msg(WorkerExecResponse::Success),
+                        Ok((status_code, stats)) => match status_code.verdict() {
+                            None => send_msg(WorkerExecResponse::Success(stats)),
+                            Some(verdict) => send_msg(WorkerExecResponse::Verdict(verdict)),
+                        },
                         Err(e) => send_msg(WorkerExecResponse::Error(format!("{e:?}"))),
                     }
                 }
@@ -76,8 +96,10 @@ fn run(
 }
 
 fn stdin_chunk(chunk: Vec<u8>) {
-    if let Some(stdin) = &*state().stdin.borrow_mut() {
-        stdin.write(&chunk);
+    if let Some(stdin_feeder) = &*state().stdin_feeder.borrow() {
+        if stdin_feeder.unbounded_send(chunk).is_err() {
+            info!("Received stdin chunk but stdin is closed");
+        }
     } else {
         warn!("Received stdin chunk but no pipe is set");
     }
@@ -89,6 +111,7 @@ fn cancel() {
     } else {
         warn!("Received cancel message but no execution is running");
     }
+    state().stdin_feeder.borrow_mut().take();
     if let Some(stdin) = state().stdin.borrow_mut().take() {
         stdin.close();
     }
@@ -2,9 +2,10 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use anyhow::{Context, Result};
-use common::{ExecConfig, File};
+use common::{ExecConfig, ExecStats, File};
 
-use crate::os::{CachedModule, FdEntry, Fs, FsEntry, Pipe, ProcessHandle};
+use crate::module_cache::get_module;
+use crate::os::{CachedModule, FdEntry, Fs, Pipe, ProcessHandle, StatusCode};
 use crate::util::*;
 
 async fn compile(llvm: CachedModule, fs: Fs, file: &str) -> Result<Vec<u8>> {
@@ -124,19 +125,23 @@ async fn link(llvm: CachedModule, mut fs: Fs, compiled: Vec<(String, Vec<u8>)>)
     Ok(linked)
 }
 
-pub async fn run(config: ExecConfig, files: Vec<File>, stdin: Pipe, stdout: Pipe) -> Result<()> {
+pub async fn run(
+    config: ExecConfig,
+    files: Vec<File>,
+    stdin: Pipe,
+    stdout: Pipe,
+) -> Result<(StatusCode, ExecStats)> {
     send_fetching_compiler();
     let fs = get_fs("cpp")
         .await
         .context("Failed to get C/C++ filesystem")?;
 
     send_compiling();
-    let llvm_exe = fs
-        .get_file_with_path(b"/bin/llvm")
+    let llvm_module = get_module("cpp", &fs, b"/bin/llvm")
+        .await
         .context("Failed to get clang executable")?;
-    let llvm_module =
-        CachedModule::from_code(&llvm_exe).expect("could not create module from wasm bytes");
 
+    let mut solution_fs = super::solution_fs(&files);
     let mut compiled = Vec::new();
     let names = files
         .iter()
@@ -162,25 +167,24 @@ pub async fn run(config: ExecConfig, files: Vec<File>, stdin: Pipe, stdout: Pipe
         .context("Linking failed")?;
 
     send_running();
-    let mut fs = Fs::new();
-    fs.add_entry_with_path(b"input.txt", FsEntry::Pipe(stdin.clone()));
-    fs.add_entry_with_path(b"output.txt", FsEntry::Pipe(stdout.clone()));
+    let (solution_stdin, solution_stdout) =
+        super::solution_io(&mut solution_fs, &config.io_mode, stdin, stdout).await?;
     let proc = ProcessHandle::builder()
         .name("solution")
-        .fs(fs_workdir)
-        .stdin(FdEntry::Pipe(stdin))
-        .stdout(FdEntry::Pipe(stdout))
+        .fs(solution_fs)
+        .stdin(solution_stdin)
+        .stdout(solution_stdout)
         .stderr(FdEntry::WriteFn(Rc::new(move |buf: &[u8]| {
             send_stderr(buf);
             buf.len()
         })))
         .mem_limit(config.mem_limit)
         .time_limit(config.time_limit)
+        .output_limit(config.output_limit)
+        .seed(config.deterministic_seed())
         .spawn_with_code(&linked);
 
-    let status_code = proc.proc.wait().await;
-    status_code.check_success().context("Execution failed")?;
-    Ok(())
+    Ok(proc.proc.wait_with_stats().await)
 }
 
 pub async fn run_ls(
@@ -216,6 +220,9 @@ pub async fn run_ls(
             .into_bytes(),
         ),
     );
+    let clangd_module = get_module("cpp", &fs, b"/bin/clangd")
+        .await
+        .context("Failed to get clangd executable")?;
     let proc = ProcessHandle::builder()
         .name("clangd")
         .fs(fs)
@@ -224,7 +231,7 @@ pub async fn run_ls(
         .stderr(FdEntry::Pipe(stderr))
         .arg("clangd")
         .arg("--pch-storage=memory")
-        .spawn_with_path(b"bin/clangd");
+        .spawn_with_module(clangd_module);
 
     crate::send_msg(common::WorkerLSResponse::Started);
     let status_code = proc.proc.wait().await;
@@ -1,10 +1,11 @@
 use std::ops::Deref;
 use std::rc::Rc;
 
-use anyhow::{Result, bail};
-use common::{ExecConfig, File, Language};
+use anyhow::{Context, Result, bail};
+use common::{ExecConfig, ExecStats, File, IoMode, Language};
 
-use crate::os::{Fs, Pipe};
+use crate::os::{FdEntry, Fs, FsEntry, Pipe, StatusCode};
+use crate::util::send_stderr;
 
 mod cpp;
 mod python;
@@ -19,6 +20,76 @@ fn mirror_workdir(fs: &mut Fs, files: Vec<File>) {
     }
 }
 
+/// Makes the sources in `/workdir` read-only, so that the solution cannot modify them.
+fn protect_workdir(fs: &mut Fs) {
+    fs.add_dir_with_path(b"/workdir");
+    fs.set_read_only_with_path(b"/workdir").unwrap();
+}
+
+/// Filesystem for running a compiled solution. It only contains a read-only copy of the sources
+/// in `/workdir`, so that the solution cannot see or modify the toolchain or the sources. Python
+/// solutions are the exception, as they run in the interpreter: they get a clone of the Python
+/// filesystem instead, whose toolchain directories are read-only (see [`get_fs`]).
+///
+/// [`get_fs`]: crate::util::get_fs
+fn solution_fs(files: &[File]) -> Fs {
+    let mut fs = Fs::new();
+    mirror_workdir(&mut fs, files.to_vec());
+    protect_workdir(&mut fs);
+    fs
+}
+
+/// Connect the solution's input and output according to `io_mode`, returning its standard input
+/// and output. In file mode the input and output files are added to the root of `fs`, standard
+/// input is empty and standard output is reported as standard error. The whole input is read
+/// before returning, so that the input file is a regular file that can be seeked and whose size
+/// is known; the output file is a pipe, so that the output is shown while the solution runs.
+pub async fn solution_io(
+    fs: &mut Fs,
+    io_mode: &IoMode,
+    stdin: Pipe,
+    stdout: Pipe,
+) -> Result<(FdEntry, FdEntry)> {
+    match io_mode {
+        IoMode::Stdio => Ok((FdEntry::Pipe(stdin), FdEntry::Pipe(stdout))),
+        IoMode::Files { input, output } => {
+            io_mode.validate().context("Invalid input/output file")?;
+            // Devices are added when the process is spawned; add them now so that their directory
+            // cannot be replaced by one of the files.
+            fs.add_devices();
+            for name in [input, output] {
+                if fs.get(fs.root(), name.as_bytes()).is_ok() {
+                    bail!("Cannot use {name:?} as input/output file: it already exists");
+                }
+            }
+            let mut data = Vec::new();
+            loop {
+                let len = stdin
+                    .fill_buf(|buf| {
+                        data.extend_from_slice(buf);
+                        buf.len()
+                    })
+                    .await;
+                if len == 0 {
+                    break;
+                }
+            }
+            fs.add_file_with_path(input.as_bytes(), Rc::new(data));
+            fs.add_entry_with_path(output.as_bytes(), FsEntry::Pipe(stdout));
+            Ok((
+                FdEntry::Data {
+                    data: Vec::new(),
+                    offset: 0,
+                },
+                FdEntry::WriteFn(Rc::new(|buf: &[u8]| {
+                    send_stderr(buf);
+                    buf.len()
+                })),
+            ))
+        }
+    }
+}
+
 pub async fn run(
     language: String,
     config: ExecConfig,
@@ -26,7 +97,7 @@ pub async fn run(
     primary_file: String,
     stdin: Pipe,
     stdout: Pipe,
-) -> Result<()> {
+) -> Result<(StatusCode, ExecStats)> {
     match language.deref() {
         "C" => cpp::run(config, files, stdin, stdout).await,
         "C++" => cpp::run(config, files, stdin, stdout).await,
@@ -1,9 +1,10 @@
 use std::rc::Rc;
 
 use anyhow::{Context, Result};
-use common::{ExecConfig, File};
+use common::{ExecConfig, ExecStats, File};
 
-use crate::os::{FdEntry, FsEntry, Pipe, ProcessHandle};
+use crate::module_cache::get_module;
+use crate::os::{FdEntry, Pipe, ProcessHandle, StatusCode};
 use crate::util::*;
 
 pub async fn run(
@@ -12,7 +13,7 @@ pub async fn run(
     primary_file: String,
     stdin: Pipe,
     stdout: Pipe,
-) -> Result<()> {
+) -> Result<(StatusCode, ExecStats)> {
     send_fetching_compiler();
     let mut fs = get_fs("python")
         .await
@@ -20,13 +21,17 @@ pub async fn run(
 
     send_running();
     super::mirror_workdir(&mut fs, files);
-    fs.add_entry_with_path(b"input.txt", FsEntry::Pipe(stdin.clone()));
-    fs.add_entry_with_path(b"output.txt", FsEntry::Pipe(stdout.clone()));
+    super::protect_workdir(&mut fs);
+    let (solution_stdin, solution_stdout) =
+        super::solution_io(&mut fs, &config.io_mode, stdin, stdout).await?;
+    let python_module = get_module("python", &fs, b"/bin/python3.13.wasm")
+        .await
+        .context("Failed to get Python executable")?;
     let proc = ProcessHandle::builder()
         .name("python3")
         .fs(fs)
-        .stdin(FdEntry::Pipe(stdin))
-        .stdout(FdEntry::Pipe(stdout))
+        .stdin(solution_stdin)
+        .stdout(solution_stdout)
         .stderr(FdEntry::WriteFn(Rc::new(move |buf: &[u8]| {
             send_stderr(buf);
             buf.len()
@@ -36,11 +41,11 @@ pub async fn run(
         .arg(format!("/workdir/{primary_file}"))
         .mem_limit(config.mem_limit)
         .time_limit(config.time_limit)
-        .spawn_with_path(b"bin/python3.13.wasm");
+        .output_limit(config.output_limit)
+        .seed(config.deterministic_seed())
+        .spawn_with_module(python_module);
 
-    let status_code = proc.proc.wait().await;
-    status_code.check_success().context("Execution failed")?;
-    Ok(())
+    Ok(proc.proc.wait_with_stats().await)
 }
 
 pub async fn run_ls(files: Vec<File>, stdin: Pipe, stdout: Pipe, stderr: Pipe) -> Result<()> {
@@ -50,6 +55,9 @@ pub async fn run_ls(files: Vec<File>, stdin: Pipe, stdout: Pipe, stderr: Pipe) -
         .context("Failed to get Python filesystem")?;
     super::mirror_workdir(&mut fs, files);
     fs.add_file_with_path(b"/ruff.toml", Rc::new(b"indent-width = 2".to_vec()));
+    let ty_module = get_module("python", &fs, b"/bin/ty.wasm")
+        .await
+        .context("Failed to get ty executable")?;
     let proc = ProcessHandle::builder()
         .name("ty")
         .fs(fs)
@@ -58,7 +66,7 @@ pub async fn run_ls(files: Vec<File>, stdin: Pipe, stdout: Pipe, stderr: Pipe) -
         .stderr(FdEntry::Pipe(stderr))
         .arg("ty")
         .arg("server")
-        .spawn_with_path(b"bin/ty.wasm");
+        .spawn_with_module(ty_module);
 
     crate::send_msg(common::WorkerLSResponse::Started);
     let status_code = proc.proc.wait().await;
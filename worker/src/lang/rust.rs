@@ -1,9 +1,10 @@
 use std::rc::Rc;
 
 use anyhow::{Context, Result};
-use common::{ExecConfig, File};
+use common::{ExecConfig, ExecStats, File};
 
-use crate::os::{FdEntry, Fs, FsEntry, Pipe, ProcessHandle};
+use crate::module_cache::get_module;
+use crate::os::{FdEntry, FsEntry, Pipe, ProcessHandle, StatusCode};
 use crate::util::*;
 
 pub async fn run(
@@ -12,13 +13,14 @@ pub async fn run(
     primary_file: String,
     stdin: Pipe,
     stdout: Pipe,
-) -> Result<()> {
+) -> Result<(StatusCode, ExecStats)> {
     send_fetching_compiler();
     let mut fs = get_fs("rust")
         .await
         .context("Failed to get Rust filesystem")?;
 
     send_compiling();
+    let mut solution_fs = super::solution_fs(&files);
     for file in files {
         fs.add_file_with_path(
             format!("/workdir/{}", file.name).as_bytes(),
@@ -27,6 +29,9 @@ pub async fn run(
     }
     let compiled_pipe = Pipe::new();
     fs.add_entry_with_path(b"__compiled", FsEntry::Pipe(compiled_pipe.clone()));
+    let rustc_module = get_module("rust", &fs, b"/bin/rustc")
+        .await
+        .context("Failed to get rustc executable")?;
     let proc = ProcessHandle::builder()
         .name("rustc")
         .fs(fs)
@@ -43,42 +48,48 @@ pub async fn run(
         .arg("--color=always")
         .arg(format!("workdir/{}", primary_file))
         .arg("-o__compiled")
-        .spawn_with_path(b"bin/rustc");
-
-    let status_code = proc.proc.wait().await;
-    status_code.check_success()?;
+        .spawn_with_module(rustc_module);
 
-    let mut compiled = Vec::new();
-    compiled_pipe.close();
-    loop {
-        let mut buf = [0u8; 4096];
-        let len = compiled_pipe.read(&mut buf).await;
-        if len == 0 {
-            break;
+    // The pipe is bounded, so the output must be drained while rustc runs.
+    let wait = async {
+        let status_code = proc.proc.wait().await;
+        compiled_pipe.close();
+        status_code
+    };
+    let read = async {
+        let mut compiled = Vec::new();
+        loop {
+            let mut buf = [0u8; 4096];
+            let len = compiled_pipe.read(&mut buf).await;
+            if len == 0 {
+                break;
+            }
+            compiled.extend_from_slice(&buf[..len]);
         }
-        compiled.extend_from_slice(&buf[..len]);
-    }
+        compiled
+    };
+    let (status_code, compiled) = futures::join!(wait, read);
+    status_code.check_success()?;
 
     send_running();
-    let mut fs = Fs::new();
-    fs.add_entry_with_path(b"input.txt", FsEntry::Pipe(stdin.clone()));
-    fs.add_entry_with_path(b"output.txt", FsEntry::Pipe(stdout.clone()));
+    let (solution_stdin, solution_stdout) =
+        super::solution_io(&mut solution_fs, &config.io_mode, stdin, stdout).await?;
     let proc = ProcessHandle::builder()
         .name("solution")
-        .fs(fs)
-        .stdin(FdEntry::Pipe(stdin))
-        .stdout(FdEntry::Pipe(stdout))
+        .fs(solution_fs)
+        .stdin(solution_stdin)
+        .stdout(solution_stdout)
         .stderr(FdEntry::WriteFn(Rc::new(move |buf: &[u8]| {
             send_stderr(buf);
             buf.len()
         })))
         .mem_limit(config.mem_limit)
         .time_limit(config.time_limit)
+        .output_limit(config.output_limit)
+        .seed(config.deterministic_seed())
         .spawn_with_code(&compiled);
 
-    let status_code = proc.proc.wait().await;
-    status_code.check_success().context("Execution failed")?;
-    Ok(())
+    Ok(proc.proc.wait_with_stats().await)
 }
 
 pub async fn run_ls(files: Vec<File>, _stdin: Pipe, _stdout: Pipe, _stderr: Pipe) -> Result<()> {
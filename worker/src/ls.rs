@@ -2,6 +2,7 @@ use std::cell::RefCell;
 
 use anyhow::{Context, Result};
 use common::{File, WorkerLSRequest, WorkerLSResponse};
+use futures::channel::mpsc::UnboundedSender;
 use futures::channel::oneshot::{Sender, channel};
 use futures::{FutureExt, select};
 use tracing::{debug, info, warn};
@@ -14,6 +15,7 @@ use crate::{lang, send_msg};
 pub struct WorkerStateLS {
     stop: RefCell<Option<Sender<()>>>,
     stdin: RefCell<Option<Pipe>>,
+    stdin_feeder: RefCell<Option<UnboundedSender<Vec<u8>>>>,
 }
 
 fn state() -> &'static WorkerStateLS {
@@ -43,6 +45,7 @@ fn start(lang: String, code_dir: String) {
     let (sender, mut receiver) = channel();
     state().stop.borrow_mut().replace(sender);
     let stdin = Pipe::new();
+    state().stdin_feeder.borrow_mut().replace(stdin.feeder());
     state().stdin.borrow_mut().replace(stdin.clone());
     let stdout = Pipe::new();
     let stderr = Pipe::new();
@@ -125,10 +128,13 @@ fn start(lang: String, code_dir: String) {
 }
 
 fn message(msg: String) {
-    if let Some(stdin) = &*state().stdin.borrow_mut() {
+    if let Some(stdin_feeder) = &*state().stdin_feeder.borrow() {
         debug!("Received LS message: {}", msg);
-        stdin.write(format!("Content-Length: {}\r\n\r\n", msg.len()).as_bytes());
-        stdin.write(msg.as_bytes());
+        let mut data = format!("Content-Length: {}\r\n\r\n", msg.len()).into_bytes();
+        data.extend_from_slice(msg.as_bytes());
+        if stdin_feeder.unbounded_send(data).is_err() {
+            warn!("Received LS message but the LS stdin is closed");
+        }
     } else {
         warn!("Received LS message but no pipe is set");
     }
@@ -138,6 +144,7 @@ fn stop() {
     if let Some(s) = state().stop.borrow_mut().take() {
         let _ = s.send(());
     }
+    state().stdin_feeder.borrow_mut().take();
     if let Some(stdin) = state().stdin.borrow_mut().take() {
         stdin.close();
     }
@@ -15,11 +15,13 @@ use web_sys::{DedicatedWorkerGlobalScope, MessageEvent};
 
 use crate::exec::{WorkerStateExec, handle_exec_request};
 use crate::ls::{WorkerStateLS, handle_ls_request};
-use crate::os::Fs;
+use crate::module_cache::ModuleCache;
+use crate::os::{Fs, prewarm_threads};
 
 mod exec;
 mod lang;
 mod ls;
+mod module_cache;
 mod os;
 mod util;
 
@@ -29,6 +31,7 @@ pub mod test;
 struct WorkerState {
     send_msg: UnboundedSender<WorkerResponse>,
     fs_cache: Mutex<HashMap<String, Fs>>,
+    module_cache: Mutex<ModuleCache>,
     exec: WorkerStateExec,
     ls: WorkerStateLS,
 }
@@ -50,12 +53,16 @@ fn main() {
         .set(SendWrapper::new(WorkerState {
             send_msg: s,
             fs_cache: Mutex::new(HashMap::new()),
+            module_cache: Mutex::new(HashMap::new()),
             exec: WorkerStateExec::default(),
             ls: WorkerStateLS::default(),
         }))
         .ok()
         .expect("worker state already initialized");
 
+    // The main thread of the program being judged is started for every run and every test.
+    prewarm_threads(["solution [1]"]);
+
     let worker = js_sys::global()
         .dyn_into::<DedicatedWorkerGlobalScope>()
         .expect("not a worker");
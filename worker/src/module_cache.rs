@@ -0,0 +1,140 @@
+//! Cache of compiled toolchain modules, kept in memory and, when the browser supports
+//! structured-cloning `WebAssembly.Module`, persisted to IndexedDB across sessions.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use anyhow::{Context, Result, anyhow};
+use js_sys::{Function, Object, Promise, Reflect};
+use tracing::{info, warn};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{IdbDatabase, IdbObjectStore, IdbRequest, IdbTransactionMode};
+
+use crate::os::{CachedModule, Fs};
+use crate::state;
+use crate::util::{js_error, sha256};
+
+const DB_NAME: &str = "module-cache";
+/// Bumped whenever the way modules are compiled changes, which discards the stored ones.
+const DB_VERSION: u32 = 2;
+const STORE_NAME: &str = "modules";
+
+/// Compiled toolchain modules by executable, with the code they were compiled from.
+pub type ModuleCache = HashMap<String, (Rc<Vec<u8>>, CachedModule)>;
+
+/// Waits for an IndexedDB request to complete, returning its result.
+async fn wait_request(req: &IdbRequest) -> Result<JsValue> {
+    let promise = Promise::new(&mut |resolve: Function, reject: Function| {
+        req.set_onsuccess(Some(&resolve));
+        req.set_onerror(Some(&reject));
+    });
+    let res = JsFuture::from(promise).await;
+    req.set_onsuccess(None);
+    req.set_onerror(None);
+    match res {
+        Ok(_) => req.result().map_err(js_error),
+        Err(_) => Err(match req.error() {
+            Ok(Some(e)) => anyhow!("{}: {}", e.name(), e.message()),
+            _ => anyhow!("IndexedDB request failed"),
+        }),
+    }
+}
+
+async fn open_store(mode: IdbTransactionMode) -> Result<IdbObjectStore> {
+    let factory = js_sys::global()
+        .unchecked_into::<web_sys::WorkerGlobalScope>()
+        .indexed_db()
+        .map_err(js_error)?
+        .context("IndexedDB is not available")?;
+    let req = factory
+        .open_with_u32(DB_NAME, DB_VERSION)
+        .map_err(js_error)?;
+    let upgrade_req = req.clone();
+    let on_upgrade = Closure::once_into_js(move || {
+        let db = upgrade_req
+            .result()
+            .expect("missing database")
+            .unchecked_into::<IdbDatabase>();
+        if db.object_store_names().contains(STORE_NAME) {
+            db.delete_object_store(STORE_NAME)
+                .expect("failed to delete object store");
+        }
+        db.create_object_store(STORE_NAME)
+            .expect("failed to create object store");
+    });
+    req.set_onupgradeneeded(Some(on_upgrade.unchecked_ref()));
+    let db = wait_request(&req).await?.unchecked_into::<IdbDatabase>();
+    db.transaction_with_str_and_mode(STORE_NAME, mode)
+        .map_err(js_error)?
+        .object_store(STORE_NAME)
+        .map_err(js_error)
+}
+
+/// Loads the module stored under `key` if it was compiled from the given version of the code.
+async fn load(key: &str, version: &str) -> Result<Option<CachedModule>> {
+    let store = open_store(IdbTransactionMode::Readonly).await?;
+    let record = wait_request(&store.get(&key.into()).map_err(js_error)?).await?;
+    if record.is_undefined() {
+        return Ok(None);
+    }
+    let stored_version = Reflect::get(&record, &"version".into()).map_err(js_error)?;
+    if stored_version.as_string().as_deref() != Some(version) {
+        return Ok(None);
+    }
+    let module = Reflect::get(&record, &"module".into()).map_err(js_error)?;
+    Ok(CachedModule::from_js_value(&module))
+}
+
+async fn store(key: &str, version: &str, module: &CachedModule) -> Result<()> {
+    let record = Object::new();
+    Reflect::set(&record, &"version".into(), &version.into()).map_err(js_error)?;
+    Reflect::set(&record, &"module".into(), &module.to_js_value()).map_err(js_error)?;
+    let store = open_store(IdbTransactionMode::Readwrite).await?;
+    // Browsers that cannot serialize modules fail here with a `DataCloneError`.
+    let req = store.put_with_key(&record, &key.into()).map_err(js_error)?;
+    wait_request(&req).await?;
+    Ok(())
+}
+
+/// Returns the compiled module for the executable at `path` in the filesystem of `tool`,
+/// compiling it only if neither the in-memory nor the persistent cache has it.
+pub async fn get_module(tool: &str, fs: &Fs, path: &[u8]) -> Result<CachedModule> {
+    let key = format!("{tool}:{}", String::from_utf8_lossy(path));
+    let code = fs
+        .get_file_with_path(path)
+        .with_context(|| format!("Failed to read {key}"))?;
+
+    // Filesystems of the same toolchain share the contents of their files, so the code is only
+    // hashed the first time it is seen.
+    let mut module_cache = state().module_cache.lock().await;
+    if let Some((cached_code, module)) = module_cache.get(&key)
+        && Rc::ptr_eq(cached_code, &code)
+    {
+        return Ok(module.clone());
+    }
+
+    let version = sha256(&code)
+        .await
+        .with_context(|| format!("Failed to hash {key}"))?;
+    let module = match load(&key, &version).await {
+        Ok(Some(module)) => {
+            info!("Loaded {key} from the module cache");
+            module
+        }
+        res => {
+            if let Err(e) = res {
+                warn!("Failed to load {key} from the module cache: {e:?}");
+            }
+            let module = CachedModule::from_code(&code)
+                .with_context(|| format!("Failed to compile {key}"))?;
+            if let Err(e) = store(&key, &version, &module).await {
+                info!("Not persisting {key} in the module cache: {e:?}");
+            }
+            module
+        }
+    };
+    module_cache.insert(key, (code, module.clone()));
+    Ok(module)
+}
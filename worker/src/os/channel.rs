@@ -0,0 +1,96 @@
+//! The buffer shared between the host and each thread of a process.
+//!
+//! Besides the word used to return syscall results, it holds two buffers that let the hottest
+//! syscalls complete inside the thread, without a message round-trip to the host:
+//!
+//! - The write buffer, filled by the thread with `fd_write` calls on stdout and stderr, and
+//!   drained by the host. Each record is the fd and the length as little-endian `u32`s, followed
+//!   by the data padded to a multiple of 4 bytes. The thread appends a record and then publishes
+//!   it by advancing `WRITE_HEAD` with a compare-and-swap; the host drains the buffer by resetting
+//!   `WRITE_HEAD` to zero in the same way. The thread notifies the host with a `flush` message
+//!   when it writes to an empty buffer.
+//! - The read-ahead buffer, filled by the host with data from a stdin pipe, and consumed by the
+//!   thread with `fd_read` calls on stdin. The host only touches it while the thread is blocked
+//!   in a syscall, and moves any data left back into the pipe before handling the syscall.
+//!
+//! The layout must match `start_proc.js`.
+
+use js_sys::{Atomics, Int32Array, SharedArrayBuffer, Uint8Array};
+
+/// Index of the word used to return syscall results.
+pub const RESULT: u32 = 0;
+/// Index of the word holding the number of bytes published in the write buffer.
+const WRITE_HEAD: u32 = 1;
+/// Indices of the words holding the range of unread bytes in the read-ahead buffer.
+const READ_START: u32 = 2;
+const READ_END: u32 = 3;
+
+const WRITE_OFFSET: u32 = 16;
+/// Size of the write buffer, in bytes. Writes that do not fit go through the host.
+pub const WRITE_CAPACITY: u32 = 64 * 1024;
+const READ_OFFSET: u32 = WRITE_OFFSET + WRITE_CAPACITY;
+/// Size of the read-ahead buffer, in bytes.
+pub const READ_CAPACITY: u32 = 16 * 1024;
+
+pub fn new_channel() -> SharedArrayBuffer {
+    SharedArrayBuffer::new(READ_OFFSET + READ_CAPACITY)
+}
+
+fn bytes(channel: &SharedArrayBuffer, offset: u32, len: u32) -> Uint8Array {
+    Uint8Array::new_with_byte_offset_and_length(channel, offset, len)
+}
+
+/// Takes the writes the thread completed on its own, as (fd, data) pairs.
+pub fn take_writes(channel: &SharedArrayBuffer) -> Vec<(u32, Vec<u8>)> {
+    let words = Int32Array::new(channel);
+    let mut buf = Vec::new();
+    loop {
+        let head = Atomics::load(&words, WRITE_HEAD).expect("failed to load write head");
+        let copied = buf.len() as u32;
+        if head as u32 > copied {
+            buf.extend(bytes(channel, WRITE_OFFSET + copied, head as u32 - copied).to_vec());
+        }
+        // This only fails if the thread published more records in the meantime.
+        let prev = Atomics::compare_exchange(&words, WRITE_HEAD, head, 0)
+            .expect("failed to reset write head");
+        if prev == head {
+            break;
+        }
+    }
+
+    let mut writes = Vec::new();
+    let mut rest = &buf[..];
+    while rest.len() >= 8 {
+        let fd = u32::from_le_bytes(rest[0..4].try_into().unwrap());
+        let len = u32::from_le_bytes(rest[4..8].try_into().unwrap()) as usize;
+        let Some(data) = rest.get(8..8 + len) else {
+            break;
+        };
+        writes.push((fd, data.to_vec()));
+        rest = &rest[(8 + len.next_multiple_of(4)).min(rest.len())..];
+    }
+    writes
+}
+
+/// Makes `data`, of at most `READ_CAPACITY` bytes, available to the thread's reads of stdin.
+/// The read-ahead buffer must be empty.
+pub fn set_read_ahead(channel: &SharedArrayBuffer, data: &[u8]) {
+    let words = Int32Array::new(channel);
+    bytes(channel, READ_OFFSET, data.len() as u32).copy_from(data);
+    Atomics::store(&words, READ_START, 0).expect("failed to store read start");
+    Atomics::store(&words, READ_END, data.len() as i32).expect("failed to store read end");
+}
+
+/// Takes the data the thread has not read yet from the read-ahead buffer.
+pub fn take_read_ahead(channel: &SharedArrayBuffer) -> Vec<u8> {
+    let words = Int32Array::new(channel);
+    let start = Atomics::load(&words, READ_START).expect("failed to load read start") as u32;
+    let end = Atomics::load(&words, READ_END).expect("failed to load read end") as u32;
+    if start >= end {
+        return Vec::new();
+    }
+    let data = bytes(channel, READ_OFFSET + start, end - start).to_vec();
+    Atomics::store(&words, READ_START, 0).expect("failed to store read start");
+    Atomics::store(&words, READ_END, 0).expect("failed to store read end");
+    data
+}
@@ -0,0 +1,34 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// The realtime clock of a process, shared with its filesystem so that file timestamps agree
+/// with the time the process reads.
+#[derive(Clone, Default)]
+pub enum Clock {
+    /// The browser's wall-clock time.
+    #[default]
+    Wall,
+    /// The virtual clock of deterministic mode, holding the time it returns next.
+    Virtual(Rc<Cell<Duration>>),
+}
+
+impl Clock {
+    /// The virtual clock starts at 2000-01-01T00:00:00Z and advances by a fixed step each time
+    /// it is read, so that programs waiting for it to change still make progress.
+    const VIRTUAL_START: Duration = Duration::from_secs(946_684_800);
+    const VIRTUAL_STEP: Duration = Duration::from_micros(1);
+
+    pub fn new_virtual() -> Clock {
+        Clock::Virtual(Rc::new(Cell::new(Self::VIRTUAL_START)))
+    }
+
+    /// The current time, as nanoseconds since the UNIX epoch.
+    pub fn now(&self) -> u64 {
+        let time = match self {
+            Clock::Wall => web_time::UNIX_EPOCH.elapsed().unwrap(),
+            Clock::Virtual(next) => next.replace(next.get() + Self::VIRTUAL_STEP),
+        };
+        time.as_nanos() as u64
+    }
+}
@@ -1,25 +1,88 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
 use enum_as_inner::EnumAsInner;
 use thiserror::Error;
 use tracing::warn;
 
-use super::Pipe;
+use super::{Clock, Pipe};
 
 pub type Inode = u64;
 
+/// Maximum number of symbolic links followed while resolving a single path.
+const MAX_SYMLINK_DEPTH: usize = 40;
+
 #[derive(Clone, EnumAsInner)]
 pub enum FsEntry {
     Dir(HashMap<Vec<u8>, Inode>),
     File(Rc<Vec<u8>>),
     Pipe(Pipe),
+    Device(Device),
+    /// A symbolic link to the given path. Absolute targets are resolved from the root of the
+    /// filesystem, relative ones from the directory containing the link.
+    Symlink(Vec<u8>),
+}
+
+/// A character device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Device {
+    /// Discards writes and reads as empty.
+    Null,
+    /// Discards writes and reads as an endless sequence of zeros.
+    Zero,
+    /// Discards writes and reads as an endless sequence of random bytes.
+    Random,
+    /// The standard input of the process opening it.
+    Stdin,
+}
+
+/// Access, modification and status change times of an inode, in nanoseconds since the Unix
+/// epoch. Access times are only changed explicitly.
+#[derive(Clone, Copy, Debug)]
+pub struct Times {
+    pub atim: u64,
+    pub mtim: u64,
+    pub ctim: u64,
+}
+
+impl Times {
+    pub fn at(time: u64) -> Times {
+        Times {
+            atim: time,
+            mtim: time,
+            ctim: time,
+        }
+    }
+}
+
+/// An inode of the filesystem.
+#[derive(Clone)]
+struct Node {
+    entry: FsEntry,
+    /// The directory containing the inode. The root is its own parent.
+    parent: Inode,
+    times: Times,
 }
 
+/// An in-memory filesystem, made of an immutable base layer shared between clones and a
+/// writable upper layer with the inodes changed since the last [`Fs::freeze`]. Cloning only
+/// copies the upper layer.
 #[derive(Clone)]
 pub struct Fs {
-    pub entries: Vec<FsEntry>,
-    pub parent_pointers: Vec<Inode>,
+    base: Rc<Vec<Node>>,
+    /// Inodes created or modified on top of `base`.
+    upper: HashMap<Inode, Node>,
+    /// Number of inodes, counting both layers.
+    len: Inode,
+    /// Inodes that were removed from their directory but may still be referenced by open
+    /// file descriptors.
+    orphans: HashSet<Inode>,
+    /// Inodes whose slots can be reused by new entries.
+    free_inodes: Vec<Inode>,
+    /// Roots of the subtrees that processes may not modify.
+    read_only: HashSet<Inode>,
+    /// Clock for the timestamps of changes.
+    clock: Clock,
 }
 
 #[derive(Debug, Error)]
@@ -32,32 +95,118 @@ pub enum FsError {
     DoesNotExist,
     #[error("File exists")]
     Exist,
+    #[error("Directory not empty")]
+    NotEmpty,
+    #[error("Invalid argument")]
+    Invalid,
+    #[error("Too many levels of symbolic links")]
+    Loop,
+    #[error("Read-only file system")]
+    ReadOnly,
 }
 
 impl Fs {
     pub fn new() -> Fs {
+        let clock = Clock::default();
+        let root = Node {
+            entry: FsEntry::Dir(HashMap::new()),
+            parent: 0,
+            times: Times::at(clock.now()),
+        };
         Fs {
-            entries: vec![FsEntry::Dir(HashMap::new())],
-            parent_pointers: vec![0],
+            base: Rc::new(vec![root]),
+            upper: HashMap::new(),
+            len: 1,
+            orphans: HashSet::new(),
+            free_inodes: Vec::new(),
+            read_only: HashSet::new(),
+            clock,
         }
     }
 
+    /// Takes the timestamps of later changes from `clock`.
+    pub fn set_clock(&mut self, clock: Clock) {
+        self.clock = clock;
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
     pub fn root(&self) -> Inode {
         0
     }
 
+    fn node(&self, inode: Inode) -> &Node {
+        self.upper
+            .get(&inode)
+            .unwrap_or_else(|| &self.base[inode as usize])
+    }
+
+    /// Returns `inode` for modification, copying it to the upper layer first if needed.
+    fn node_mut(&mut self, inode: Inode) -> &mut Node {
+        self.upper
+            .entry(inode)
+            .or_insert_with(|| self.base[inode as usize].clone())
+    }
+
+    pub fn entry(&self, inode: Inode) -> &FsEntry {
+        &self.node(inode).entry
+    }
+
+    pub fn entry_mut(&mut self, inode: Inode) -> &mut FsEntry {
+        &mut self.node_mut(inode).entry
+    }
+
+    fn parent(&self, inode: Inode) -> Inode {
+        self.node(inode).parent
+    }
+
+    pub fn times(&self, inode: Inode) -> Times {
+        self.node(inode).times
+    }
+
+    pub fn times_mut(&mut self, inode: Inode) -> &mut Times {
+        &mut self.node_mut(inode).times
+    }
+
+    /// Merges the upper layer into a new base layer, so that clones made from now on start
+    /// with an empty upper layer.
+    pub fn freeze(&mut self) {
+        if self.upper.is_empty() {
+            return;
+        }
+        let mut base: Vec<Node> = (*self.base).clone();
+        let mut upper: Vec<_> = std::mem::take(&mut self.upper).into_iter().collect();
+        upper.sort_unstable_by_key(|(inode, _)| *inode);
+        for (inode, node) in upper {
+            if inode as usize == base.len() {
+                base.push(node);
+            } else {
+                base[inode as usize] = node;
+            }
+        }
+        self.base = Rc::new(base);
+    }
+
     pub fn add_file_with_path(&mut self, path: &[u8], data: Rc<Vec<u8>>) {
         self.add_entry_with_path(path, FsEntry::File(data));
     }
 
-    pub fn add_entry_with_path(&mut self, path: &[u8], entry: FsEntry) {
-        let components: Vec<_> = path.split(|x| *x == b'/').map(|x| x.to_vec()).collect();
+    pub fn add_entry_with_path(&mut self, path: &[u8], entry: FsEntry) -> Inode {
+        let (dirs, name) = match path.iter().rposition(|x| *x == b'/') {
+            Some(pos) => (&path[..pos], &path[pos + 1..]),
+            None => (&b""[..], path),
+        };
+        let parent = self.add_dir_with_path(dirs);
+        self.add_entry(parent, name, entry)
+    }
+
+    /// Creates the directory at `path` along with any missing parent, returning its inode.
+    pub fn add_dir_with_path(&mut self, path: &[u8]) -> Inode {
         let mut cur = self.root();
-        for c in &components[..components.len() - 1] {
-            if c.is_empty() {
-                continue;
-            }
-            let FsEntry::Dir(dir) = &mut self.entries[cur as usize] else {
+        for c in path.split(|x| *x == b'/').filter(|x| !x.is_empty()) {
+            let FsEntry::Dir(dir) = self.entry(cur) else {
                 warn!("invalid file set");
                 panic!("invalid files");
             };
@@ -67,7 +216,7 @@ impl Fs {
                 cur = self.add_entry(cur, c, FsEntry::Dir(HashMap::new()));
             }
         }
-        self.add_entry(cur, components.last().unwrap(), entry);
+        cur
     }
 
     pub fn get_file_with_path(&self, path: &[u8]) -> Result<Rc<Vec<u8>>, FsError> {
@@ -77,130 +226,461 @@ impl Fs {
         Ok(data)
     }
 
+    /// Opens the entry at `path`, creating an empty file there if it does not exist and `creat`
+    /// is set. When following a symbolic link whose target does not exist, the target is
+    /// created instead.
     pub fn open(
         &mut self,
-        mut parent: Inode,
+        parent: Inode,
         path: &[u8],
+        follow_symlinks: bool,
         creat: bool,
         excl: bool,
     ) -> Result<Inode, FsError> {
-        let mut dirs: Vec<&[u8]> = path
-            .split(|x| *x == b'/')
-            .filter(|x| !x.is_empty())
-            .collect();
-        let name = dirs.pop();
-        for cur in dirs {
-            let FsEntry::Dir(dir) = &self.entries[parent as usize] else {
-                return Err(FsError::NotDir);
-            };
-            if cur == b"." {
-            } else if cur == b".." {
-                parent = self.parent_pointers[parent as usize];
-            } else if let Some(child) = dir.get(cur) {
-                parent = *child;
-            } else {
-                return Err(FsError::DoesNotExist);
-            };
-        }
-        let Some(name) = name else {
-            return Ok(parent);
-        };
-        let FsEntry::Dir(dir) = &mut self.entries[parent as usize] else {
-            return Err(FsError::NotDir);
-        };
+        self.open_inner(parent, path, follow_symlinks, creat, excl, &mut 0)
+    }
+
+    fn open_inner(
+        &mut self,
+        parent: Inode,
+        path: &[u8],
+        follow_symlinks: bool,
+        creat: bool,
+        excl: bool,
+        depth: &mut usize,
+    ) -> Result<Inode, FsError> {
+        let (parent, name) = self.walk(parent, path, depth)?;
+        let dir = self.entry(parent).as_dir().unwrap();
         if name == b"." {
             Ok(parent)
         } else if name == b".." {
-            Ok(self.parent_pointers[parent as usize])
-        } else if let Some(file) = dir.get(name) {
+            Ok(self.parent(parent))
+        } else if let Some(&file) = dir.get(name) {
             if excl {
                 return Err(FsError::Exist);
             }
-            Ok(*file)
+            match self.entry(file) {
+                FsEntry::Symlink(target) if follow_symlinks => {
+                    *depth += 1;
+                    if *depth > MAX_SYMLINK_DEPTH {
+                        return Err(FsError::Loop);
+                    }
+                    let start = if target.starts_with(b"/") {
+                        self.root()
+                    } else {
+                        parent
+                    };
+                    let target = target.clone();
+                    self.open_inner(start, &target, true, creat, false, depth)
+                }
+                _ => Ok(file),
+            }
         } else if creat {
+            self.check_writable(parent)?;
             Ok(self.add_entry(parent, name, FsEntry::File(Rc::new(Vec::new()))))
         } else {
             Err(FsError::DoesNotExist)
         }
     }
 
-    pub fn create_dir(&mut self, mut parent: Inode, path: &[u8]) -> Result<Inode, FsError> {
-        let mut dirs = path.split(|x| *x == b'/').filter(|x| !x.is_empty());
-        let Some(name) = dirs.next_back() else {
+    pub fn create_dir(&mut self, parent: Inode, path: &[u8]) -> Result<Inode, FsError> {
+        let (parent, name) = self.resolve_parent(parent, path)?;
+        let dir = self.entry(parent).as_dir().unwrap();
+        if name == b"." || name == b".." || dir.contains_key(name) {
             return Err(FsError::Exist);
-        };
-        for cur in dirs {
-            let FsEntry::Dir(dir) = &self.entries[parent as usize] else {
-                return Err(FsError::NotDir);
-            };
-            if cur == b"." {
-            } else if cur == b".." {
-                parent = self.parent_pointers[parent as usize];
-            } else if let Some(&child) = dir.get(cur) {
-                parent = child;
-            } else {
-                return Err(FsError::DoesNotExist);
+        }
+        self.check_writable(parent)?;
+        Ok(self.add_entry(parent, name, FsEntry::Dir(HashMap::new())))
+    }
+
+    /// Moves the entry at `old_path` (relative to `old_parent`) to `new_path` (relative to
+    /// `new_parent`), replacing the destination if it exists and is compatible.
+    pub fn rename(
+        &mut self,
+        old_parent: Inode,
+        old_path: &[u8],
+        new_parent: Inode,
+        new_path: &[u8],
+    ) -> Result<(), FsError> {
+        let (old_parent, old_name) = self.resolve_parent(old_parent, old_path)?;
+        let (new_parent, new_name) = self.resolve_parent(new_parent, new_path)?;
+        if matches!(old_name, b"." | b"..") || matches!(new_name, b"." | b"..") {
+            return Err(FsError::Invalid);
+        }
+        let inode = *self
+            .entry(old_parent)
+            .as_dir()
+            .unwrap()
+            .get(old_name)
+            .ok_or(FsError::DoesNotExist)?;
+        self.check_writable(old_parent)?;
+        self.check_writable(inode)?;
+        self.check_writable(new_parent)?;
+        let is_dir = self.entry(inode).is_dir();
+        if is_dir {
+            // A directory cannot be moved inside itself.
+            let mut cur = new_parent;
+            while cur != self.root() {
+                if cur == inode {
+                    return Err(FsError::Invalid);
+                }
+                cur = self.parent(cur);
             }
         }
-        let FsEntry::Dir(dir) = &mut self.entries[parent as usize] else {
-            return Err(FsError::NotDir);
-        };
+        let new_dir = self.entry(new_parent).as_dir().unwrap();
+        if let Some(&existing) = new_dir.get(new_name) {
+            if existing == inode {
+                return Ok(());
+            }
+            match (is_dir, self.entry(existing)) {
+                (true, FsEntry::Dir(dir)) if !dir.is_empty() => return Err(FsError::NotEmpty),
+                (true, FsEntry::Dir(_)) => {}
+                (true, _) => return Err(FsError::NotDir),
+                (false, FsEntry::Dir(_)) => return Err(FsError::IsDir),
+                (false, _) => {}
+            }
+            self.orphans.insert(existing);
+        }
+        self.entry_mut(old_parent)
+            .as_dir_mut()
+            .unwrap()
+            .remove(old_name);
+        self.entry_mut(new_parent)
+            .as_dir_mut()
+            .unwrap()
+            .insert(new_name.to_vec(), inode);
+        self.node_mut(inode).parent = new_parent;
+        self.touch(old_parent);
+        self.touch(new_parent);
+        self.node_mut(inode).times.ctim = self.clock.now();
+        Ok(())
+    }
+
+    /// Removes the non-directory entry at `path`, returning its inode. The inode is only
+    /// reclaimed by [`Fs::release_orphans`].
+    pub fn remove_file(&mut self, parent: Inode, path: &[u8]) -> Result<Inode, FsError> {
+        let (parent, name) = self.resolve_parent(parent, path)?;
+        if matches!(name, b"." | b"..") {
+            return Err(FsError::IsDir);
+        }
+        let dir = self.entry(parent).as_dir().unwrap();
+        let inode = *dir.get(name).ok_or(FsError::DoesNotExist)?;
+        if self.entry(inode).is_dir() {
+            return Err(FsError::IsDir);
+        }
+        self.check_writable(inode)?;
+        self.detach(parent, name, inode);
+        Ok(inode)
+    }
+
+    /// Removes the empty directory at `path`, returning its inode. The inode is only
+    /// reclaimed by [`Fs::release_orphans`].
+    pub fn remove_dir(&mut self, parent: Inode, path: &[u8]) -> Result<Inode, FsError> {
+        let (parent, name) = self.resolve_parent(parent, path)?;
+        if name == b"." {
+            return Err(FsError::Invalid);
+        }
+        if name == b".." {
+            return Err(FsError::NotEmpty);
+        }
+        let dir = self.entry(parent).as_dir().unwrap();
+        let inode = *dir.get(name).ok_or(FsError::DoesNotExist)?;
+        match self.entry(inode) {
+            FsEntry::Dir(dir) if !dir.is_empty() => return Err(FsError::NotEmpty),
+            FsEntry::Dir(_) => {}
+            _ => return Err(FsError::NotDir),
+        }
+        self.check_writable(inode)?;
+        self.detach(parent, name, inode);
+        Ok(inode)
+    }
+
+    /// Frees every removed inode for which `in_use` returns false, making its slot available
+    /// for new entries.
+    pub fn release_orphans(&mut self, in_use: impl Fn(Inode) -> bool) {
+        loop {
+            let released: Vec<_> = self
+                .orphans
+                .iter()
+                .copied()
+                .filter(|&inode| !in_use(inode))
+                .collect();
+            if released.is_empty() {
+                break;
+            }
+            for inode in released {
+                self.orphans.remove(&inode);
+                let entry = std::mem::replace(self.entry_mut(inode), FsEntry::Dir(HashMap::new()));
+                // Entries created through a descriptor of an already removed directory.
+                if let FsEntry::Dir(children) = entry {
+                    self.orphans.extend(children.into_values());
+                }
+                self.read_only.remove(&inode);
+                self.free_inodes.push(inode);
+            }
+        }
+    }
+
+    fn detach(&mut self, parent: Inode, name: &[u8], inode: Inode) {
+        self.entry_mut(parent).as_dir_mut().unwrap().remove(name);
+        self.orphans.insert(inode);
+        self.touch(parent);
+    }
+
+    pub fn get(&self, parent: Inode, path: &[u8]) -> Result<Inode, FsError> {
+        self.lookup(parent, path, true)
+    }
+
+    /// Resolves `path` relative to `parent`. Symbolic links in intermediate components are
+    /// always followed, while the last component is only followed if `follow_symlinks` is set.
+    pub fn lookup(
+        &self,
+        parent: Inode,
+        path: &[u8],
+        follow_symlinks: bool,
+    ) -> Result<Inode, FsError> {
+        self.lookup_inner(parent, path, follow_symlinks, &mut 0)
+    }
+
+    pub fn symlink(&mut self, parent: Inode, path: &[u8], target: &[u8]) -> Result<Inode, FsError> {
+        let (parent, name) = self.resolve_parent(parent, path)?;
+        let dir = self.entry(parent).as_dir().unwrap();
         if name == b"." || name == b".." || dir.contains_key(name) {
             return Err(FsError::Exist);
         }
-        Ok(self.add_entry(parent, name, FsEntry::Dir(HashMap::new())))
+        self.check_writable(parent)?;
+        Ok(self.add_entry(parent, name, FsEntry::Symlink(target.to_vec())))
     }
 
-    pub fn get(&self, parent: Inode, path: &[u8]) -> Result<Inode, FsError> {
-        if path.is_empty() {
+    pub fn read_link(&self, parent: Inode, path: &[u8]) -> Result<&[u8], FsError> {
+        let inode = self.lookup(parent, path, false)?;
+        match self.entry(inode) {
+            FsEntry::Symlink(target) => Ok(target),
+            _ => Err(FsError::Invalid),
+        }
+    }
+
+    fn lookup_inner(
+        &self,
+        parent: Inode,
+        path: &[u8],
+        follow_symlinks: bool,
+        depth: &mut usize,
+    ) -> Result<Inode, FsError> {
+        let (parent, name) = self.walk(parent, path, depth)?;
+        if name == b"." {
             return Ok(parent);
         }
-        let FsEntry::Dir(dir) = &self.entries[parent as usize] else {
-            return Err(FsError::NotDir);
-        };
-        let mut path = path.splitn(2, |x| *x == b'/');
-        let cur = path.next().unwrap();
-        let rest = path.next().unwrap_or(b"");
-        if cur == b"." || cur == b"" {
-            self.get(parent, rest)
-        } else if cur == b".." {
-            self.get(self.parent_pointers[parent as usize], rest)
-        } else if let Some(child) = dir.get(cur) {
-            self.get(*child, rest)
+        if name == b".." {
+            return Ok(self.parent(parent));
+        }
+        let dir = self.entry(parent).as_dir().unwrap();
+        let child = *dir.get(name).ok_or(FsError::DoesNotExist)?;
+        if follow_symlinks {
+            self.follow(parent, child, depth)
         } else {
-            Err(FsError::DoesNotExist)
+            Ok(child)
         }
     }
 
+    /// Returns `inode` itself, or the inode it eventually points to if it is a symbolic link
+    /// contained in `dir`.
+    fn follow(&self, dir: Inode, inode: Inode, depth: &mut usize) -> Result<Inode, FsError> {
+        let FsEntry::Symlink(target) = self.entry(inode) else {
+            return Ok(inode);
+        };
+        *depth += 1;
+        if *depth > MAX_SYMLINK_DEPTH {
+            return Err(FsError::Loop);
+        }
+        let start = if target.starts_with(b"/") {
+            self.root()
+        } else {
+            dir
+        };
+        self.lookup_inner(start, target, true, depth)
+    }
+
     pub fn get_file(&self, inode: Inode) -> Result<Rc<Vec<u8>>, FsError> {
-        match &self.entries[inode as usize] {
+        match self.entry(inode) {
             FsEntry::File(f) => Ok(f.clone()),
             FsEntry::Dir(_) => Err(FsError::IsDir),
             FsEntry::Pipe(_) => todo!(),
+            FsEntry::Device(_) | FsEntry::Symlink(_) => Err(FsError::Invalid),
         }
     }
 
+    /// Resizes the file at `inode`, padding it with zeros if it grows. Contents shared with
+    /// other clones of the filesystem are copied rather than modified in place.
+    pub fn set_file_size(&mut self, inode: Inode, size: usize) -> Result<(), FsError> {
+        self.check_writable(inode)?;
+        match self.entry(inode) {
+            FsEntry::File(data) if data.len() == size => return Ok(()),
+            FsEntry::File(_) => {}
+            FsEntry::Dir(_) => return Err(FsError::IsDir),
+            FsEntry::Pipe(_) | FsEntry::Device(_) | FsEntry::Symlink(_) => {
+                return Err(FsError::Invalid);
+            }
+        }
+        let data = self.entry_mut(inode).as_file_mut().unwrap();
+        if let Some(data) = Rc::get_mut(data) {
+            data.resize(size, 0);
+        } else {
+            let mut new_data = data[..size.min(data.len())].to_vec();
+            new_data.resize(size, 0);
+            *data = Rc::new(new_data);
+        }
+        self.touch(inode);
+        Ok(())
+    }
+
+    /// Records that the contents of `inode` were just modified.
+    pub fn touch(&mut self, inode: Inode) {
+        let now = self.clock.now();
+        let times = &mut self.node_mut(inode).times;
+        times.mtim = now;
+        times.ctim = now;
+    }
+
+    /// Sets the access and/or modification time of `inode`.
+    pub fn set_times(
+        &mut self,
+        inode: Inode,
+        atim: Option<u64>,
+        mtim: Option<u64>,
+    ) -> Result<(), FsError> {
+        self.check_writable(inode)?;
+        let now = self.clock.now();
+        let times = &mut self.node_mut(inode).times;
+        if let Some(atim) = atim {
+            times.atim = atim;
+        }
+        if let Some(mtim) = mtim {
+            times.mtim = mtim;
+        }
+        times.ctim = now;
+        Ok(())
+    }
+
+    /// Adds the standard device files to `/dev`, which becomes read-only. Existing entries are
+    /// left untouched.
+    pub fn add_devices(&mut self) {
+        const DEVICES: [(&[u8], Device); 5] = [
+            (b"null", Device::Null),
+            (b"zero", Device::Zero),
+            (b"random", Device::Random),
+            (b"urandom", Device::Random),
+            (b"stdin", Device::Stdin),
+        ];
+        let dev = self.add_dir_with_path(b"/dev");
+        for (name, device) in DEVICES {
+            if !self.entry(dev).as_dir().unwrap().contains_key(name) {
+                self.add_entry(dev, name, FsEntry::Device(device));
+            }
+        }
+        self.read_only.insert(dev);
+    }
+
+    /// Makes the subtree at `path` read-only for processes. The host can still modify it.
+    pub fn set_read_only_with_path(&mut self, path: &[u8]) -> Result<(), FsError> {
+        let inode = self.get(self.root(), path)?;
+        self.read_only.insert(inode);
+        Ok(())
+    }
+
+    /// Returns whether `inode` is inside a read-only subtree.
+    pub fn is_read_only(&self, mut inode: Inode) -> bool {
+        loop {
+            if self.read_only.contains(&inode) {
+                return true;
+            }
+            // Removed entries are no longer part of any subtree.
+            if inode == self.root() || self.orphans.contains(&inode) {
+                return false;
+            }
+            inode = self.parent(inode);
+        }
+    }
+
+    fn check_writable(&self, inode: Inode) -> Result<(), FsError> {
+        match self.is_read_only(inode) {
+            true => Err(FsError::ReadOnly),
+            false => Ok(()),
+        }
+    }
+
+    /// Walks all but the last component of `path`, returning the directory that contains
+    /// the entry and the entry's name.
+    fn resolve_parent<'a>(
+        &self,
+        parent: Inode,
+        path: &'a [u8],
+    ) -> Result<(Inode, &'a [u8]), FsError> {
+        self.walk(parent, path, &mut 0)
+    }
+
+    fn walk<'a>(
+        &self,
+        mut parent: Inode,
+        path: &'a [u8],
+        depth: &mut usize,
+    ) -> Result<(Inode, &'a [u8]), FsError> {
+        let mut dirs = path.split(|x| *x == b'/').filter(|x| !x.is_empty());
+        let name = dirs.next_back().unwrap_or(b".");
+        for cur in dirs {
+            let FsEntry::Dir(dir) = self.entry(parent) else {
+                return Err(FsError::NotDir);
+            };
+            if cur == b"." {
+            } else if cur == b".." {
+                parent = self.parent(parent);
+            } else if let Some(&child) = dir.get(cur) {
+                parent = self.follow(parent, child, depth)?;
+            } else {
+                return Err(FsError::DoesNotExist);
+            }
+        }
+        if !self.entry(parent).is_dir() {
+            return Err(FsError::NotDir);
+        }
+        Ok((parent, name))
+    }
+
     fn add_entry(&mut self, parent: Inode, name: &[u8], entry: FsEntry) -> Inode {
-        let new_entry = self.entries.len() as Inode;
-        self.entries.push(entry);
-        self.parent_pointers.push(parent);
-        let FsEntry::Dir(dir) = &mut self.entries[parent as usize] else {
+        let times = Times::at(self.clock.now());
+        let new_entry = self.free_inodes.pop().unwrap_or_else(|| {
+            self.len += 1;
+            self.len - 1
+        });
+        self.upper.insert(
+            new_entry,
+            Node {
+                entry,
+                parent,
+                times,
+            },
+        );
+        let FsEntry::Dir(dir) = self.entry_mut(parent) else {
             panic!("invalid call to add_entry");
         };
         dir.insert(name.to_vec(), new_entry);
+        self.touch(parent);
         new_entry
     }
 
-    pub fn get_name(&self, mut inode: Inode) -> Vec<u8> {
+    /// Returns the absolute path of `inode`. Fails if it, or one of its ancestors, was removed.
+    pub fn get_name(&self, mut inode: Inode) -> Result<Vec<u8>, FsError> {
         let mut rev_name = Vec::new();
         while inode > 0 {
-            let p = self.parent_pointers[inode as usize];
-            let n = self.entries[p as usize]
+            if self.orphans.contains(&inode) {
+                return Err(FsError::DoesNotExist);
+            }
+            let p = self.parent(inode);
+            let n = self
+                .entry(p)
                 .as_dir()
-                .unwrap()
-                .iter()
-                .find(|(_, v)| **v == inode)
-                .unwrap()
+                .and_then(|dir| dir.iter().find(|(_, v)| **v == inode))
+                .ok_or(FsError::DoesNotExist)?
                 .0;
             rev_name.push(n);
             inode = p;
@@ -215,7 +695,7 @@ impl Fs {
         if name.is_empty() {
             name.extend(b"/");
         }
-        name
+        Ok(name)
     }
 }
 
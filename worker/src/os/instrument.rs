@@ -0,0 +1,188 @@
+//! Instrumentation of the modules run by processes, so that the host learns about failed
+//! `memory.grow` instructions. These only return -1 to the module, which then usually exits
+//! with an error or aborts, and would otherwise be indistinguishable from any other failure.
+
+use std::ops::Range;
+
+use anyhow::Result;
+use wasmparser::{BinaryReader, Operator, Parser, Payload, TypeRef};
+
+/// Name of the global exported by instrumented modules, set to 1 once a `memory.grow` failed.
+pub const GROW_FAILED_GLOBAL: &str = "__memory_grow_failed";
+
+const TYPE_SECTION: u8 = 1;
+const FUNCTION_SECTION: u8 = 3;
+const GLOBAL_SECTION: u8 = 6;
+const EXPORT_SECTION: u8 = 7;
+const CODE_SECTION: u8 = 10;
+
+/// `(func (param i32) (result i32))`
+const HOOK_TYPE: [u8; 5] = [0x60, 0x01, 0x7f, 0x01, 0x7f];
+/// `(global (mut i32) (i32.const 0))`
+const FLAG_GLOBAL: [u8; 5] = [0x7f, 0x01, 0x41, 0x00, 0x0b];
+
+fn write_u32(out: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn write_section(out: &mut Vec<u8>, id: u8, content: &[u8]) {
+    out.push(id);
+    write_u32(out, content.len() as u32);
+    out.extend_from_slice(content);
+}
+
+/// Appends `item` to the vector that makes up the contents of a section.
+fn append_item(content: &[u8], item: &[u8]) -> Result<Vec<u8>> {
+    let mut reader = BinaryReader::new(content, 0);
+    let count = reader.read_var_u32()?;
+    let mut out = Vec::with_capacity(content.len() + item.len() + 5);
+    write_u32(&mut out, count + 1);
+    out.extend_from_slice(&content[reader.current_position()..]);
+    out.extend_from_slice(item);
+    Ok(out)
+}
+
+/// Position of a non-custom section in the order required by the binary format.
+fn section_rank(id: u8) -> u8 {
+    match id {
+        // Tag sections come between the memory and global sections, and data count sections
+        // before the code section.
+        13 => 6,
+        6..=9 => id + 1,
+        12 => 11,
+        10 | 11 => id + 2,
+        _ => id,
+    }
+}
+
+/// Returns a copy of `code` in which every `memory.grow` of the default memory goes through a
+/// function that sets the exported global [`GROW_FAILED_GLOBAL`] if growing fails, or `None` if
+/// the module never grows its memory.
+pub fn instrument_memory_grow(code: &[u8]) -> Result<Option<Vec<u8>>> {
+    let mut sections: Vec<(u8, Range<usize>)> = Vec::new();
+    let mut bodies = Vec::new();
+    let mut grows = Vec::new();
+    let mut num_types = 0;
+    let mut num_funcs = 0;
+    let mut num_globals = 0;
+    for payload in Parser::new(0).parse_all(code) {
+        let payload = payload?;
+        match &payload {
+            Payload::TypeSection(s) => {
+                for group in s.clone() {
+                    num_types += group?.types().len() as u32;
+                }
+            }
+            Payload::ImportSection(s) => {
+                for import in s.clone().into_imports() {
+                    match import?.ty {
+                        TypeRef::Func(_) | TypeRef::FuncExact(_) => num_funcs += 1,
+                        TypeRef::Global(_) => num_globals += 1,
+                        _ => {}
+                    }
+                }
+            }
+            Payload::FunctionSection(s) => num_funcs += s.count(),
+            Payload::GlobalSection(s) => num_globals += s.count(),
+            Payload::CodeSectionEntry(body) => {
+                let mut ops = body.get_operators_reader()?;
+                while !ops.eof() {
+                    let (op, start) = ops.read_with_offset()?;
+                    if let Operator::MemoryGrow { mem: 0 } = op {
+                        grows.push(start..ops.original_position());
+                    }
+                }
+                bodies.push(body.range());
+            }
+            _ => {}
+        }
+        if let Some(section) = payload.as_section() {
+            sections.push(section);
+        }
+    }
+    if grows.is_empty() {
+        return Ok(None);
+    }
+
+    // The hook is added after every other function and global, so existing indices are kept.
+    let mut hook_type = Vec::new();
+    write_u32(&mut hook_type, num_types);
+    let mut call = vec![0x10];
+    write_u32(&mut call, num_funcs);
+    let mut export = Vec::new();
+    write_u32(&mut export, GROW_FAILED_GLOBAL.len() as u32);
+    export.extend_from_slice(GROW_FAILED_GLOBAL.as_bytes());
+    export.push(0x03);
+    write_u32(&mut export, num_globals);
+    // (local $result i32)
+    // (local.tee $result (memory.grow (local.get 0)))
+    // (if (i32.eq (i32.const -1)) (then (global.set $flag (i32.const 1))))
+    // (local.get $result)
+    let mut hook = vec![
+        0x01, 0x01, 0x7f, 0x20, 0x00, 0x40, 0x00, 0x22, 0x01, 0x41, 0x7f, 0x46, 0x04, 0x40, 0x41,
+        0x01, 0x24,
+    ];
+    write_u32(&mut hook, num_globals);
+    hook.extend_from_slice(&[0x0b, 0x20, 0x01, 0x0b]);
+
+    let mut out = Vec::with_capacity(code.len() + grows.len() * 4 + 64);
+    out.extend_from_slice(&code[..8]);
+    let mut grows = grows.into_iter().peekable();
+    let mut has_global_section = false;
+    let mut has_export_section = false;
+    for (id, range) in sections {
+        let content = &code[range];
+        if id != 0 {
+            // Modules without globals or exports get sections of their own.
+            if !has_global_section && section_rank(id) > section_rank(GLOBAL_SECTION) {
+                write_section(&mut out, GLOBAL_SECTION, &append_item(&[0], &FLAG_GLOBAL)?);
+                has_global_section = true;
+            }
+            if !has_export_section && section_rank(id) > section_rank(EXPORT_SECTION) {
+                write_section(&mut out, EXPORT_SECTION, &append_item(&[0], &export)?);
+                has_export_section = true;
+            }
+        }
+        match id {
+            TYPE_SECTION => write_section(&mut out, id, &append_item(content, &HOOK_TYPE)?),
+            FUNCTION_SECTION => write_section(&mut out, id, &append_item(content, &hook_type)?),
+            GLOBAL_SECTION => {
+                write_section(&mut out, id, &append_item(content, &FLAG_GLOBAL)?);
+                has_global_section = true;
+            }
+            EXPORT_SECTION => {
+                write_section(&mut out, id, &append_item(content, &export)?);
+                has_export_section = true;
+            }
+            CODE_SECTION => {
+                let mut section = Vec::with_capacity(content.len() + grows.len() * 4 + 32);
+                write_u32(&mut section, bodies.len() as u32 + 1);
+                for body in &bodies {
+                    let mut new_body = Vec::with_capacity(body.len() + 16);
+                    let mut pos = body.start;
+                    while let Some(grow) = grows.next_if(|grow| grow.start < body.end) {
+                        new_body.extend_from_slice(&code[pos..grow.start]);
+                        new_body.extend_from_slice(&call);
+                        pos = grow.end;
+                    }
+                    new_body.extend_from_slice(&code[pos..body.end]);
+                    write_u32(&mut section, new_body.len() as u32);
+                    section.extend_from_slice(&new_body);
+                }
+                write_u32(&mut section, hook.len() as u32);
+                section.extend_from_slice(&hook);
+                write_section(&mut out, id, &section);
+            }
+            _ => write_section(&mut out, id, content),
+        }
+    }
+    Ok(Some(out))
+}
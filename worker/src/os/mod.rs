@@ -1,8 +1,14 @@
+mod channel;
+mod clock;
 mod fs;
+mod instrument;
 mod pipe;
 mod process;
 mod syscall;
+mod thread_pool;
 
+pub use clock::Clock;
 pub use fs::*;
 pub use pipe::*;
 pub use process::*;
+pub use thread_pool::prewarm_threads;
@@ -1,21 +1,60 @@
 use std::cell::RefCell;
 use std::collections::VecDeque;
 use std::future::poll_fn;
-use std::io::{Read, Write};
+use std::io::Read;
 use std::ops::Deref;
 use std::rc::Rc;
 use std::task::{Poll, Waker};
 
+use futures::StreamExt;
+use futures::channel::mpsc::{UnboundedSender, unbounded};
 use futures::lock::Mutex;
+use thiserror::Error;
+use wasm_bindgen_futures::spawn_local;
+
+/// Maximum number of bytes buffered in a pipe. Writers wait for readers to make room.
+pub const PIPE_CAPACITY: usize = 64 * 1024;
+
+#[derive(Debug, Error)]
+#[error("Broken pipe")]
+pub struct PipeClosed;
 
 struct PipeCell {
     buf: VecDeque<u8>,
     closed: bool,
-    reader: Option<Waker>,
+    readers: Vec<Waker>,
+    writers: Vec<Waker>,
+}
+
+impl PipeCell {
+    fn add_reader(&mut self, waker: &Waker) {
+        if !self.readers.iter().any(|w| w.will_wake(waker)) {
+            self.readers.push(waker.clone());
+        }
+    }
+
+    fn wake_readers(&mut self) {
+        for reader in self.readers.drain(..) {
+            reader.wake();
+        }
+    }
+
+    fn add_writer(&mut self, waker: &Waker) {
+        if !self.writers.iter().any(|w| w.will_wake(waker)) {
+            self.writers.push(waker.clone());
+        }
+    }
+
+    fn wake_writers(&mut self) {
+        for writer in self.writers.drain(..) {
+            writer.wake();
+        }
+    }
 }
 
 pub struct PipeInner {
     reader_mutex: Mutex<()>,
+    writer_mutex: Mutex<()>,
     cell: RefCell<PipeCell>,
 }
 
@@ -24,11 +63,13 @@ impl PipeInner {
         let cell = PipeCell {
             buf: VecDeque::new(),
             closed: false,
-            reader: None,
+            readers: Vec::new(),
+            writers: Vec::new(),
         };
 
         PipeInner {
             reader_mutex: Mutex::new(()),
+            writer_mutex: Mutex::new(()),
             cell: RefCell::new(cell),
         }
     }
@@ -43,6 +84,7 @@ impl PipeInner {
                 let read = (cb.take().unwrap())(slice);
                 assert!(read <= slice.len());
                 cell.buf.drain(..read);
+                cell.wake_writers();
                 return Poll::Ready(read);
             }
             if cell.closed {
@@ -51,7 +93,7 @@ impl PipeInner {
                 return Poll::Ready(read);
             }
 
-            cell.reader = Some(cx.waker().clone());
+            cell.add_reader(cx.waker());
             Poll::Pending
         })
         .await
@@ -62,33 +104,108 @@ impl PipeInner {
         poll_fn(|cx| {
             let mut cell = self.cell.borrow_mut();
             if !cell.buf.is_empty() {
-                return Poll::Ready(cell.buf.read(buf).expect("read failed"));
+                let read = cell.buf.read(buf).expect("read failed");
+                cell.wake_writers();
+                return Poll::Ready(read);
             }
             if cell.closed {
                 return Poll::Ready(0);
             }
 
-            cell.reader = Some(cx.waker().clone());
+            cell.add_reader(cx.waker());
             Poll::Pending
         })
         .await
     }
 
-    pub fn write(&self, buf: &[u8]) {
-        let mut inner = self.cell.borrow_mut();
-        assert!(!inner.closed, "write to closed pipe");
-        inner.buf.write_all(buf).expect("write failed");
-        if let Some(reader) = inner.reader.take() {
-            reader.wake();
+    /// Reads the data that is immediately available, without waiting. Returns 0 if there is
+    /// none, or if another read is in progress.
+    pub fn try_read(&self, buf: &mut [u8]) -> usize {
+        let Some(_guard) = self.reader_mutex.try_lock() else {
+            return 0;
+        };
+        let mut cell = self.cell.borrow_mut();
+        let read = cell.buf.read(buf).expect("read failed");
+        if read > 0 {
+            cell.wake_writers();
+        }
+        read
+    }
+
+    /// Puts back data that was read, so that it is returned by the next reads. This may exceed
+    /// the pipe capacity.
+    pub fn unread(&self, data: &[u8]) {
+        let mut cell = self.cell.borrow_mut();
+        for &byte in data.iter().rev() {
+            cell.buf.push_front(byte);
+        }
+        cell.wake_readers();
+    }
+
+    /// Writes all of `buf`, waiting for room in the pipe as needed. Concurrent writes are not
+    /// interleaved. Fails if the pipe is closed before everything is written.
+    pub async fn write(&self, mut buf: &[u8]) -> Result<(), PipeClosed> {
+        let _guard = self.writer_mutex.lock().await;
+        while !buf.is_empty() {
+            let written = poll_fn(|cx| {
+                let mut cell = self.cell.borrow_mut();
+                if cell.closed {
+                    return Poll::Ready(Err(PipeClosed));
+                }
+                let room = PIPE_CAPACITY.saturating_sub(cell.buf.len());
+                if room > 0 {
+                    let len = room.min(buf.len());
+                    cell.buf.extend(&buf[..len]);
+                    cell.wake_readers();
+                    return Poll::Ready(Ok(len));
+                }
+                cell.add_writer(cx.waker());
+                Poll::Pending
+            })
+            .await?;
+            buf = &buf[written..];
         }
+        Ok(())
     }
 
     pub fn close(&self) {
         let mut inner = self.cell.borrow_mut();
         inner.closed = true;
-        if let Some(reader) = inner.reader.take() {
-            reader.wake();
-        }
+        inner.wake_readers();
+        inner.wake_writers();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.cell.borrow().closed
+    }
+
+    /// Waits until the pipe has buffered data or is closed, without consuming anything, and
+    /// returns the number of bytes that can be read.
+    pub async fn wait_readable(&self) -> usize {
+        poll_fn(|cx| {
+            let mut cell = self.cell.borrow_mut();
+            if !cell.buf.is_empty() || cell.closed {
+                return Poll::Ready(cell.buf.len());
+            }
+            cell.add_reader(cx.waker());
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Waits until the pipe has room for more data or is closed, and returns the number of bytes
+    /// that can be written without waiting.
+    pub async fn wait_writable(&self) -> usize {
+        poll_fn(|cx| {
+            let mut cell = self.cell.borrow_mut();
+            let room = PIPE_CAPACITY.saturating_sub(cell.buf.len());
+            if room > 0 || cell.closed {
+                return Poll::Ready(room);
+            }
+            cell.add_writer(cx.waker());
+            Poll::Pending
+        })
+        .await
     }
 
     pub async fn read_exact(&self, buf: &mut [u8]) -> Result<(), usize> {
@@ -135,6 +252,23 @@ impl Pipe {
         let inner = Rc::new(PipeInner::new());
         Pipe { inner }
     }
+
+    /// Returns a sender whose chunks are written to the pipe in order, in the background. The
+    /// pipe is closed once every sender is dropped. If the pipe gets closed first, pending
+    /// chunks are discarded and sending fails.
+    pub fn feeder(&self) -> UnboundedSender<Vec<u8>> {
+        let (sender, mut receiver) = unbounded::<Vec<u8>>();
+        let pipe = self.clone();
+        spawn_local(async move {
+            while let Some(chunk) = receiver.next().await {
+                if pipe.write(&chunk).await.is_err() {
+                    return;
+                }
+            }
+            pipe.close();
+        });
+        sender
+    }
 }
 
 impl Deref for Pipe {
@@ -2,20 +2,26 @@
 
 use std::cell::RefCell;
 use std::rc::Rc;
+use std::time::Duration;
 
-use anyhow::{Result, anyhow, ensure};
+use anyhow::{Context, Result, anyhow, ensure};
+use common::{ExecStats, Trap, Verdict};
 use enum_as_inner::EnumAsInner;
 use futures::channel::oneshot::{Receiver, Sender, channel};
 use futures::lock::Mutex;
 use gloo_timers::callback::Timeout;
 use js_sys::WebAssembly::{Memory, Module};
 use js_sys::{Object, Reflect, SharedArrayBuffer};
-use wasm_bindgen::JsCast;
 use wasm_bindgen::closure::Closure;
-use web_sys::{Worker, WorkerOptions, WorkerType};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{MessageEvent, Worker};
 use web_time::Instant;
 
-use super::{Fs, Inode, Pipe, syscall};
+use super::channel::new_channel;
+use super::instrument::instrument_memory_grow;
+use super::syscall::{self, Rights};
+use super::thread_pool::{acquire_worker, prewarm_threads, release_worker};
+use super::{Clock, Device, Fs, Inode, Pipe};
 
 type WriteFn = Rc<dyn Fn(&[u8]) -> usize>;
 
@@ -30,6 +36,46 @@ pub enum FdEntry {
     /// inode, offset, append
     File(Inode, usize, bool),
     Pipe(Pipe),
+    Device(Device),
+}
+
+impl FdEntry {
+    /// Returns a new descriptor for the same file. In-memory data is copied, so the two
+    /// descriptors do not share their contents or offset.
+    pub fn duplicate(&self) -> FdEntry {
+        match self {
+            FdEntry::WriteFn(f) => FdEntry::WriteFn(f.clone()),
+            FdEntry::Data { data, offset } => FdEntry::Data {
+                data: data.clone(),
+                offset: *offset,
+            },
+            FdEntry::Dir(inode) => FdEntry::Dir(*inode),
+            FdEntry::File(inode, offset, append) => FdEntry::File(*inode, *offset, *append),
+            FdEntry::Pipe(pipe) => FdEntry::Pipe(pipe.clone()),
+            FdEntry::Device(device) => FdEntry::Device(*device),
+        }
+    }
+}
+
+/// An open file descriptor, with the WASI rights that were granted to it.
+pub struct Descriptor {
+    pub entry: FdEntry,
+    /// Operations allowed on the descriptor.
+    pub rights: Rights,
+    /// Rights that descriptors opened through this one may get.
+    pub rights_inheriting: Rights,
+}
+
+impl Descriptor {
+    /// Returns a descriptor for `entry` with all the rights that apply to it.
+    pub fn new(entry: FdEntry) -> Descriptor {
+        let (rights, rights_inheriting) = Rights::for_entry(&entry);
+        Descriptor {
+            entry,
+            rights,
+            rights_inheriting,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -71,6 +117,8 @@ impl CachedModule {
             .ok_or_else(|| anyhow!("imported memory should have a maximum size"))?
             as _;
 
+        let instrumented = instrument_memory_grow(code).context("failed to instrument module")?;
+        let code = instrumented.as_deref().unwrap_or(code);
         let uint8array = js_sys::Uint8Array::new_with_length(code.len() as u32);
         uint8array.copy_from(code);
         let module = Module::new(&uint8array).expect("could not create module from wasm bytes");
@@ -81,54 +129,245 @@ impl CachedModule {
             maximum_mem,
         })
     }
+
+    /// Converts the module to an object that can be stored with the structured clone algorithm.
+    pub fn to_js_value(&self) -> JsValue {
+        let obj = Object::new();
+        Reflect::set(&obj, &"module".into(), &self.module).expect("could not set module");
+        Reflect::set(&obj, &"initial_mem".into(), &self.initial_mem.into())
+            .expect("could not set initial memory size");
+        Reflect::set(&obj, &"maximum_mem".into(), &self.maximum_mem.into())
+            .expect("could not set maximum memory size");
+        obj.into()
+    }
+
+    /// Converts back an object created by `to_js_value`.
+    pub fn from_js_value(value: &JsValue) -> Option<Self> {
+        let module = Reflect::get(value, &"module".into())
+            .ok()?
+            .dyn_into()
+            .ok()?;
+        let mem = |name: &str| Some(Reflect::get(value, &name.into()).ok()?.as_f64()? as u32);
+        Some(Self {
+            module,
+            initial_mem: mem("initial_mem")?,
+            maximum_mem: mem("maximum_mem")?,
+        })
+    }
 }
 
 pub struct Process {
     pub module: Module,
     pub memory: Memory,
+    /// Maximum number of bytes the process may write to pipes and write callbacks.
+    pub output_limit: Option<u64>,
     pub name: Option<String>,
     pub start_instant: Instant,
     pub args: Vec<Vec<u8>>,
     pub env: Vec<Vec<u8>>,
     pub termination_send: Mutex<Sender<()>>,
+    /// Flags telling threads which of stdout and stderr (indexed by fd) they may write to
+    /// through their channel, without waiting for the host.
+    pub fast_fds: SharedArrayBuffer,
+    /// Held while draining the writes threads made through their channels, to keep them in
+    /// order.
+    pub fast_write_lock: Mutex<()>,
+    /// Pipe the process was given as standard input. It is closed when the process terminates,
+    /// so that whoever is feeding it stops instead of waiting for room forever.
+    pub stdin: Option<Pipe>,
     pub inner: RefCell<ProcessInner>,
 }
 
 impl Drop for Process {
     fn drop(&mut self) {
-        self.kill(StatusCode::Signaled);
+        self.kill(StatusCode::Cancelled);
+    }
+}
+
+/// Approximate CPU time accounting for a thread: the time since it started, minus the time it
+/// spent blocked waiting for syscalls to be handled.
+pub struct ThreadTimes {
+    start: Instant,
+    blocked: Duration,
+    blocked_since: Option<Instant>,
+    exited: Option<Instant>,
+}
+
+impl ThreadTimes {
+    fn new() -> Self {
+        ThreadTimes {
+            start: Instant::now(),
+            blocked: Duration::ZERO,
+            blocked_since: None,
+            exited: None,
+        }
+    }
+
+    pub fn enter_syscall(&mut self) {
+        self.blocked_since = Some(Instant::now());
+    }
+
+    pub fn leave_syscall(&mut self) {
+        if let Some(since) = self.blocked_since.take() {
+            self.blocked += since.elapsed();
+        }
+    }
+
+    pub fn exit(&mut self) {
+        self.exited.get_or_insert_with(Instant::now);
+    }
+
+    pub fn cpu_time(&self) -> Duration {
+        let now = self
+            .blocked_since
+            .or(self.exited)
+            .unwrap_or_else(Instant::now);
+        now.duration_since(self.start).saturating_sub(self.blocked)
+    }
+}
+
+/// State of a process running in deterministic mode: randomness comes from a seeded PRNG. The
+/// realtime clock is virtual too, see [`Clock`].
+pub struct Deterministic {
+    /// SplitMix64 state.
+    rng_state: u64,
+}
+
+impl Deterministic {
+    fn new(seed: u64) -> Self {
+        Deterministic { rng_state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng_state = self.rng_state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.rng_state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    pub fn fill_random(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
     }
 }
 
 pub struct ProcessInner {
-    pub fds: Vec<Option<FdEntry>>,
+    pub fds: Vec<Option<Descriptor>>,
     pub status_code: StatusCode,
-    pub threads: Vec<Worker>,
+    /// Thread workers, indexed by thread id minus one. A worker is returned to the thread pool,
+    /// leaving `None`, once its thread has finished.
+    pub threads: Vec<Option<Worker>>,
+    /// Channels shared with the threads, indexed by thread id minus one.
+    pub channels: Vec<SharedArrayBuffer>,
+    /// CPU time accounting, indexed by thread id minus one.
+    pub thread_times: Vec<ThreadTimes>,
     pub termination_recv: Receiver<()>,
+    /// When the process was killed or exited.
+    pub end_instant: Option<Instant>,
+    /// Number of bytes written to pipes and write callbacks, counted against `output_limit`.
+    pub output_written: u64,
+    pub deterministic: Option<Deterministic>,
+    /// Whether a `memory.grow` failed in any thread. A process failing after that is assumed to
+    /// have failed because an allocation did.
+    pub memory_grow_failed: bool,
     pub fs: Fs,
 }
 
 impl ProcessInner {
-    pub fn add_fd(&mut self, entry: FdEntry) -> u32 {
+    pub fn add_fd(&mut self, descriptor: Descriptor) -> u32 {
         for fd in 0..self.fds.len() {
             if self.fds[fd].is_none() {
-                self.fds[fd] = Some(entry);
+                self.fds[fd] = Some(descriptor);
                 return fd as u32;
             }
         }
-        self.fds.push(Some(entry));
+        self.fds.push(Some(descriptor));
         self.fds.len() as u32 - 1
     }
+
+    pub fn cpu_time(&self) -> Duration {
+        self.thread_times.iter().map(ThreadTimes::cpu_time).sum()
+    }
+
+    /// Reclaims the inodes of removed files and directories that are no longer open.
+    pub fn release_orphans(&mut self) {
+        let ProcessInner { fds, fs, .. } = self;
+        fs.release_orphans(|inode| {
+            fds.iter().flatten().any(|fd| match fd.entry {
+                FdEntry::Dir(i) | FdEntry::File(i, _, _) => i == inode,
+                _ => false,
+            })
+        });
+    }
 }
 
 impl Process {
-    pub fn kill(&self, status_code: StatusCode) {
+    /// Terminates the process with `status_code`. If it already terminated, its status is kept,
+    /// so that a late time limit or cancellation does not replace the status it ended with.
+    pub fn kill(&self, mut status_code: StatusCode) {
         let mut inner = self.inner.borrow_mut();
-        inner.status_code = status_code;
-        for worker in inner.threads.drain(..) {
-            worker.terminate();
+        if inner.end_instant.is_none() {
+            if status_code.is_failure() && inner.memory_grow_failed {
+                status_code = StatusCode::MemoryLimitExceeded;
+            }
+            inner.status_code = status_code;
+            inner.end_instant = Some(Instant::now());
+        }
+        // The slots are kept, so that they still match thread ids if messages already queued
+        // by the threads arrive later.
+        let mut terminated = Vec::new();
+        for (i, slot) in inner.threads.iter_mut().enumerate() {
+            if let Some(worker) = slot.take() {
+                worker.terminate();
+                terminated.push(self.thread_name(i as u32 + 1));
+            }
         }
         inner.termination_recv.close();
+        drop(inner);
+        if let Some(stdin) = &self.stdin {
+            stdin.close();
+        }
+        prewarm_threads(terminated.iter().map(String::as_str));
+    }
+
+    /// Name of the worker running thread `tid`, as shown by developer tools.
+    fn thread_name(&self, tid: u32) -> String {
+        format!("{} [{tid}]", self.name.as_deref().unwrap_or("thread"))
+    }
+
+    /// Returns the worker of a thread that has finished running to the thread pool.
+    fn release_thread(&self, tid: u32) {
+        let worker = self
+            .inner
+            .borrow_mut()
+            .threads
+            .get_mut(tid as usize - 1)
+            .and_then(Option::take);
+        if let Some(worker) = worker {
+            release_worker(self.thread_name(tid), worker);
+        }
+    }
+
+    /// Current size of the memory, in pages of 64KiB. As memory never shrinks, this is also its
+    /// peak size.
+    pub fn memory_pages(&self) -> u32 {
+        let buffer: SharedArrayBuffer = self.memory.buffer().unchecked_into();
+        buffer.byte_length() / 65536
+    }
+
+    /// Waits for the process to terminate, returning its status and the resources it used.
+    pub async fn wait_with_stats(&self) -> (StatusCode, ExecStats) {
+        let status_code = self.wait().await;
+        let end_instant = self.inner.borrow().end_instant.unwrap_or_else(Instant::now);
+        let stats = ExecStats {
+            time: end_instant.duration_since(self.start_instant).as_secs_f64(),
+            memory: Some(self.memory_pages() as u64 * 65536),
+            exit_code: status_code.as_exited().copied(),
+        };
+        (status_code, stats)
     }
 
     pub async fn wait(&self) -> StatusCode {
@@ -138,22 +377,17 @@ impl Process {
     }
 
     pub fn spawn_thread(self: &Rc<Self>, arg: Option<i32>) -> u32 {
-        let tid = self.inner.borrow().threads.len() as u32 + 1;
+        let tid = self.inner.borrow().thread_times.len() as u32 + 1;
 
-        let channel = SharedArrayBuffer::new(4);
+        let channel = new_channel();
 
-        let path = wasm_bindgen::link_to!(module = "/src/os/start_proc.js");
-        let options = WorkerOptions::default();
-        options.set_type(WorkerType::Module);
-        if let Some(name) = &self.name {
-            options.set_name(&format!("{name} [{tid}]"));
-        }
-        let worker = Worker::new_with_options(&path, &options).expect("couldn't start thread");
+        let worker = acquire_worker(&self.thread_name(tid));
 
         let msg = Object::new();
         Reflect::set(&msg, &"module".into(), &self.module).expect("could not set module");
         Reflect::set(&msg, &"memory".into(), &self.memory).expect("could not set memory");
         Reflect::set(&msg, &"channel".into(), &channel).expect("could not set channel");
+        Reflect::set(&msg, &"fast_fds".into(), &self.fast_fds).expect("could not set fast fds");
         if let Some(arg) = arg {
             Reflect::set(&msg, &"tid".into(), &tid.into()).expect("could not set argument");
             Reflect::set(&msg, &"arg".into(), &arg.into()).expect("could not set argument");
@@ -163,17 +397,34 @@ impl Process {
             .expect("failed sending init message to worker");
 
         let proc = Rc::downgrade(self);
+        let thread_channel = channel.clone();
         worker.set_onmessage(Some(
-            Closure::<dyn Fn(_)>::new(move |msg| {
-                if let Some(proc) = proc.upgrade() {
-                    syscall::handle_message(proc, channel.clone(), msg);
+            Closure::<dyn Fn(_)>::new(move |msg: JsValue| {
+                let Some(proc) = proc.upgrade() else {
+                    return;
+                };
+                // The last message of a thread is marked as `done`, after which its worker is
+                // idle and can be reused.
+                let data = msg.unchecked_ref::<MessageEvent>().data();
+                let flag =
+                    |name: &str| Reflect::get(&data, &name.into()).is_ok_and(|f| f.is_truthy());
+                // Threads report failed `memory.grow` instructions along with their syscalls.
+                if flag("grow_failed") {
+                    proc.inner.borrow_mut().memory_grow_failed = true;
+                }
+                if flag("done") {
+                    proc.release_thread(tid);
                 }
+                syscall::handle_message(proc, tid, thread_channel.clone(), msg);
             })
             .into_js_value()
             .unchecked_ref(),
         ));
 
-        self.inner.borrow_mut().threads.push(worker);
+        let mut inner = self.inner.borrow_mut();
+        inner.threads.push(Some(worker));
+        inner.channels.push(channel);
+        inner.thread_times.push(ThreadTimes::new());
 
         tid
     }
@@ -196,10 +447,11 @@ pub struct Builder {
     env: Vec<Vec<u8>>,
     mem_limit: Option<u32>,
     time_limit: Option<f64>,
+    output_limit: Option<u64>,
+    seed: Option<u64>,
 }
 
 impl Builder {
-    #[allow(dead_code)]
     pub fn name(mut self, name: impl Into<String>) -> Self {
         self.name = Some(name.into());
         self
@@ -263,59 +515,87 @@ impl Builder {
         self
     }
 
+    /// Set the maximum number of bytes the process may write to its output pipes and callbacks.
+    pub fn output_limit(mut self, output_limit: Option<u64>) -> Self {
+        self.output_limit = output_limit;
+        self
+    }
+
+    /// Run the process in deterministic mode with the given seed.
+    pub fn seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
+        self
+    }
+
     pub fn spawn_with_module(self, module: CachedModule) -> ProcessHandle {
+        let maximum_mem = self.mem_limit.unwrap_or(65536).min(module.maximum_mem);
         let mem_opts = Object::new();
         Reflect::set(&mem_opts, &"initial".into(), &module.initial_mem.into())
             .expect("could not set initial memory size");
-        Reflect::set(
-            &mem_opts,
-            &"maximum".into(),
-            &self
-                .mem_limit
-                .unwrap_or(65536)
-                .min(module.maximum_mem)
-                .into(),
-        )
-        .expect("could not set maximum memory size");
+        Reflect::set(&mem_opts, &"maximum".into(), &maximum_mem.into())
+            .expect("could not set maximum memory size");
         Reflect::set(&mem_opts, &"shared".into(), &true.into())
             .expect("could not set shared memory option");
         let memory = Memory::new(&mem_opts).expect("could not create memory");
 
         let start_instant = Instant::now();
 
-        let fs = self.fs.unwrap_or_default();
+        let mut fs = self.fs.unwrap_or_default();
+        fs.set_clock(match self.seed {
+            Some(_) => Clock::new_virtual(),
+            None => Clock::Wall,
+        });
+        fs.add_devices();
 
-        let mut fds = vec![self.stdin, self.stdout, self.stderr];
+        let stdin = match &self.stdin {
+            Some(FdEntry::Pipe(pipe)) => Some(pipe.clone()),
+            _ => None,
+        };
+        let mut fds: Vec<_> = [self.stdin, self.stdout, self.stderr]
+            .into_iter()
+            .map(|entry| entry.map(Descriptor::new))
+            .collect();
         if let Some(preopen) = self.preopen {
             for path in preopen {
                 let inode = fs.get(fs.root(), &path).unwrap();
-                assert!(fs.entries[inode as usize].is_dir());
-                fds.push(Some(FdEntry::Dir(inode)));
+                assert!(fs.entry(inode).is_dir());
+                fds.push(Some(Descriptor::new(FdEntry::Dir(inode))));
             }
         } else {
-            fds.push(Some(FdEntry::Dir(fs.root())));
+            fds.push(Some(Descriptor::new(FdEntry::Dir(fs.root()))));
         }
 
         let (termination_send, termination_recv) = channel();
 
         let inner = ProcessInner {
             fds,
-            status_code: StatusCode::Signaled,
+            status_code: StatusCode::Cancelled,
             threads: Vec::new(),
+            channels: Vec::new(),
+            thread_times: Vec::new(),
             termination_recv,
+            end_instant: None,
+            output_written: 0,
+            deterministic: self.seed.map(Deterministic::new),
+            memory_grow_failed: false,
             fs,
         };
 
         let proc = Rc::new(Process {
             module: module.module,
             memory,
+            output_limit: self.output_limit,
             name: self.name,
             start_instant,
             args: self.args,
             env: self.env,
             termination_send: Mutex::new(termination_send),
+            fast_fds: SharedArrayBuffer::new(3 * 4),
+            fast_write_lock: Mutex::new(()),
+            stdin,
             inner: RefCell::new(inner),
         });
+        syscall::update_fast_fds(&proc);
 
         proc.spawn_thread(None);
 
@@ -323,7 +603,7 @@ impl Builder {
             let proc_weak = Rc::downgrade(&proc);
             let timeout = Timeout::new((time_limit * 1000.) as _, move || {
                 if let Some(proc) = proc_weak.upgrade() {
-                    proc.kill(StatusCode::Signaled);
+                    proc.kill(StatusCode::TimeLimitExceeded);
                 };
             });
             timeout.forget();
@@ -336,11 +616,6 @@ impl Builder {
         let module = CachedModule::from_code(code).expect("failed to parse module");
         self.spawn_with_module(module)
     }
-
-    pub fn spawn_with_path(self, path: &[u8]) -> ProcessHandle {
-        let code = self.fs.as_ref().unwrap().get_file_with_path(path).unwrap();
-        self.spawn_with_code(&code)
-    }
 }
 
 impl ProcessHandle {
@@ -349,23 +624,140 @@ impl ProcessHandle {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// WASI signal number.
+pub type Signal = u8;
+
+pub const SIGABRT: Signal = 6;
+
+const SIGNAL_NAMES: [&str; 31] = [
+    "NONE",
+    "SIGHUP",
+    "SIGINT",
+    "SIGQUIT",
+    "SIGILL",
+    "SIGTRAP",
+    "SIGABRT",
+    "SIGBUS",
+    "SIGFPE",
+    "SIGKILL",
+    "SIGUSR1",
+    "SIGSEGV",
+    "SIGUSR2",
+    "SIGPIPE",
+    "SIGALRM",
+    "SIGTERM",
+    "SIGCHLD",
+    "SIGCONT",
+    "SIGSTOP",
+    "SIGTSTP",
+    "SIGTTIN",
+    "SIGTTOU",
+    "SIGURG",
+    "SIGXCPU",
+    "SIGXFSZ",
+    "SIGVTALRM",
+    "SIGPROF",
+    "SIGWINCH",
+    "SIGPOLL",
+    "SIGPWR",
+    "SIGSYS",
+];
+
+pub fn signal_name(signal: Signal) -> Option<&'static str> {
+    SIGNAL_NAMES.get(signal as usize).copied()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, EnumAsInner)]
 #[must_use]
 pub enum StatusCode {
     Exited(u32),
-    Signaled,
-    RuntimeError(String),
+    Signaled(Signal),
+    Trapped(Trap),
+    TimeLimitExceeded,
+    MemoryLimitExceeded,
+    OutputLimitExceeded,
+    Cancelled,
 }
 
 impl StatusCode {
+    /// Status of a process whose thread trapped with the given message.
+    pub fn from_trap(msg: String) -> StatusCode {
+        // Browsers do not expose the trap kind other than through the message, which differs
+        // between engines. The messages of V8, SpiderMonkey and JavaScriptCore are listed in
+        // this order; some engines end them with a period.
+        let trap = match msg.trim_end_matches('.') {
+            "unreachable" | "unreachable executed" | "Unreachable code should not be executed" => {
+                Trap::Unreachable
+            }
+            "divide by zero"
+            | "remainder by zero"
+            | "integer divide by zero"
+            | "Division by zero" => Trap::DivisionByZero,
+            "divide result unrepresentable" | "integer overflow" | "Integer overflow" => {
+                Trap::IntegerOverflow
+            }
+            "float unrepresentable in integer range"
+            | "invalid conversion to integer"
+            | "Out of bounds Trunc operation" => Trap::InvalidConversion,
+            "memory access out of bounds"
+            | "index out of bounds"
+            | "Out of bounds memory access" => Trap::OutOfBounds,
+            "Maximum call stack size exceeded" | "too much recursion" | "Stack overflow" => {
+                Trap::StackOverflow
+            }
+            "null function or function signature mismatch"
+            | "table index is out of bounds"
+            | "indirect call to null"
+            | "indirect call signature mismatch"
+            | "Out of bounds call_indirect"
+            | "call_indirect to a null table entry"
+            | "call_indirect to a signature that does not match" => Trap::BadIndirectCall,
+            _ => Trap::Other(msg),
+        };
+        StatusCode::Trapped(trap)
+    }
+
+    /// Whether the process terminated on its own because of an error.
+    fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            StatusCode::Exited(1..) | StatusCode::Signaled(SIGABRT) | StatusCode::Trapped(_)
+        )
+    }
+
+    /// The verdict for a process with this status, or `None` if it terminated successfully.
+    pub fn verdict(&self) -> Option<Verdict> {
+        Some(match self {
+            StatusCode::Exited(0) => return None,
+            StatusCode::Exited(code) => Verdict::NonZeroExit(*code),
+            StatusCode::Signaled(signal) => Verdict::Signaled(match signal_name(*signal) {
+                Some(name) => name.to_string(),
+                None => signal.to_string(),
+            }),
+            StatusCode::Trapped(trap) => Verdict::Trapped(trap.clone()),
+            StatusCode::TimeLimitExceeded => Verdict::TimeLimitExceeded,
+            StatusCode::MemoryLimitExceeded => Verdict::MemoryLimitExceeded,
+            StatusCode::OutputLimitExceeded => Verdict::OutputLimitExceeded,
+            StatusCode::Cancelled => Verdict::Cancelled,
+        })
+    }
+
     pub fn check_success(&self) -> Result<()> {
         match self {
             StatusCode::Exited(0) => Ok(()),
             StatusCode::Exited(code) => Err(anyhow!("Process exited with non-zero code: {}", code)),
-            StatusCode::Signaled => Err(anyhow!("Process was killed by a signal")),
-            StatusCode::RuntimeError(msg) => {
-                Err(anyhow!("Process encountered a runtime error: {}", msg))
+            StatusCode::Signaled(SIGABRT) => Err(anyhow!("Process aborted (SIGABRT)")),
+            StatusCode::Signaled(signal) => match signal_name(*signal) {
+                Some(name) => Err(anyhow!("Process was killed by signal {name}")),
+                None => Err(anyhow!("Process was killed by signal {signal}")),
+            },
+            StatusCode::Trapped(trap) => {
+                Err(anyhow!("Process encountered a runtime error: {}", trap))
             }
+            StatusCode::TimeLimitExceeded => Err(anyhow!("Process exceeded the time limit")),
+            StatusCode::MemoryLimitExceeded => Err(anyhow!("Process exceeded the memory limit")),
+            StatusCode::OutputLimitExceeded => Err(anyhow!("Process exceeded the output limit")),
+            StatusCode::Cancelled => Err(anyhow!("Process was cancelled")),
         }
     }
 }
@@ -1,16 +1,112 @@
+// Layout of the channel shared with the host, see `channel.rs`.
+const RESULT = 0;
+const WRITE_HEAD = 1;
+const READ_START = 2;
+const READ_END = 3;
+const WRITE_OFFSET = 16;
+const WRITE_CAPACITY = 64 * 1024;
+const READ_OFFSET = WRITE_OFFSET + WRITE_CAPACITY;
+
 let channel;
+let fastFds;
+let memory;
+// Set by instrumented modules when a `memory.grow` fails, see `instrument.rs`.
+let growFailed;
+
+function growHasFailed() {
+    return growFailed !== undefined && growFailed.value !== 0;
+}
 
 function syscall(kind) {
     return function(...args) {
         let array = new Int32Array(channel);
-        Atomics.store(array, 0, -1);
-        postMessage({ kind, args });
-        Atomics.wait(array, 0, -1);
-        const val = Atomics.load(array, 0);
+        Atomics.store(array, RESULT, -1);
+        postMessage({ kind, args, grow_failed: growHasFailed() });
+        Atomics.wait(array, RESULT, -1);
+        const val = Atomics.load(array, RESULT);
         return val;
     };
 }
 
+// Returns views of the buffers of an iovec array, or null if any of them is out of bounds.
+function iovecs(iovs, iovsLen) {
+    const mem = new Uint8Array(memory.buffer);
+    if (iovs + 8 * iovsLen > mem.length) {
+        return null;
+    }
+    const view = new DataView(memory.buffer);
+    const bufs = [];
+    for (let i = 0; i < iovsLen; i++) {
+        const buf = view.getUint32(iovs + 8 * i, true);
+        const len = view.getUint32(iovs + 8 * i + 4, true);
+        if (buf + len > mem.length) {
+            return null;
+        }
+        bufs.push(mem.subarray(buf, buf + len));
+    }
+    return bufs;
+}
+
+const slowFdWrite = syscall("fd_write");
+
+// Writes to stdout and stderr go to the channel, which the host drains, as long as they fit.
+function fdWrite(fd, iovs, iovsLen, nwritten) {
+    const bufs = (fd === 1 || fd === 2) && Atomics.load(fastFds, fd) === 1
+        && iovecs(iovs >>> 0, iovsLen >>> 0);
+    if (bufs && (nwritten >>> 0) + 4 <= memory.buffer.byteLength) {
+        const len = bufs.reduce((sum, buf) => sum + buf.length, 0);
+        const size = 8 + Math.ceil(len / 4) * 4;
+        const words = new Int32Array(channel);
+        for (;;) {
+            const head = Atomics.load(words, WRITE_HEAD);
+            if (head + size > WRITE_CAPACITY) {
+                break;
+            }
+            const record = new DataView(channel, WRITE_OFFSET + head, 8);
+            record.setUint32(0, fd, true);
+            record.setUint32(4, len, true);
+            const data = new Uint8Array(channel, WRITE_OFFSET + head + 8, len);
+            let pos = 0;
+            for (const buf of bufs) {
+                data.set(buf, pos);
+                pos += buf.length;
+            }
+            // This only fails if the host drained the channel in the meantime.
+            if (Atomics.compareExchange(words, WRITE_HEAD, head, head + size) === head) {
+                if (head === 0) {
+                    postMessage({ kind: 'flush', args: [] });
+                }
+                new DataView(memory.buffer).setUint32(nwritten >>> 0, len, true);
+                return 0;
+            }
+        }
+    }
+    return slowFdWrite(fd, iovs, iovsLen, nwritten);
+}
+
+const slowFdRead = syscall("fd_read");
+
+// Reads from stdin are served from the read-ahead buffer when the host filled it.
+function fdRead(fd, iovs, iovsLen, nread) {
+    const words = new Int32Array(channel);
+    let start = Atomics.load(words, READ_START);
+    const end = Atomics.load(words, READ_END);
+    const bufs = fd === 0 && start < end && iovecs(iovs >>> 0, iovsLen >>> 0);
+    if (bufs && (nread >>> 0) + 4 <= memory.buffer.byteLength) {
+        let total = 0;
+        for (const buf of bufs) {
+            const len = Math.min(buf.length, end - start);
+            buf.set(new Uint8Array(channel, READ_OFFSET + start, len));
+            start += len;
+            total += len;
+        }
+        Atomics.store(words, READ_START, start);
+        new DataView(memory.buffer).setUint32(nread >>> 0, total, true);
+        return 0;
+    }
+    return slowFdRead(fd, iovs, iovsLen, nread);
+}
+
 const wasip1_names = [
     "args_get", "args_sizes_get", "clock_res_get", "clock_time_get", "environ_get",
     "environ_sizes_get", "fd_advise", "fd_allocate", "fd_close", "fd_datasync", "fd_fdstat_get",
@@ -26,6 +122,8 @@ const wasip1_names = [
 const wasip1 = Object.fromEntries(
     wasip1_names.map(name => [name, syscall(name)])
 );
+wasip1.fd_write = fdWrite;
+wasip1.fd_read = fdRead;
 
 const wasi = {
     'thread-spawn': syscall("thread_spawn"),
@@ -41,16 +139,20 @@ self.onmessage = (msg) => {
             }
         };
         channel = msg.data.channel;
+        fastFds = new Int32Array(msg.data.fast_fds);
+        memory = msg.data.memory;
+        growFailed = undefined;
         let wasm = new WebAssembly.Instance(msg.data.module, imports);
+        growFailed = wasm.exports.__memory_grow_failed;
         if (msg.data.tid !== undefined) {
             wasm.exports.wasi_thread_start(msg.data.tid, msg.data.arg);
+            postMessage({ kind: 'thread_exit', args: [], done: true, grow_failed: growHasFailed() });
         } else {
             wasm.exports._start();
-            postMessage({ kind: 'proc_exit', args: [0] });
+            postMessage({ kind: 'proc_exit', args: [0], done: true, grow_failed: growHasFailed() });
         }
     } catch (e) {
-        postMessage({ re: e.message });
-    } finally {
-        self.close();
+        // The instance is discarded, so the worker can still be reused.
+        postMessage({ re: e.message, done: true, grow_failed: growHasFailed() });
     }
 };
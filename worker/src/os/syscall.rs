@@ -1,17 +1,21 @@
 use std::rc::Rc;
 
 use bitflags::bitflags;
+use common::Trap;
 use futures::{FutureExt, StreamExt, stream::FuturesUnordered};
 use gloo_timers::future::TimeoutFuture;
 use js_sys::{Atomics, Int32Array, SharedArrayBuffer, Uint8Array};
 use serde::Deserialize;
 use wasm_bindgen::{JsCast, JsValue};
 use wasm_bindgen_futures::spawn_local;
-use web_sys::MessageEvent;
+use web_sys::{MessageEvent, WorkerGlobalScope};
 use zerocopy::{FromBytes, Immutable, IntoBytes};
 
-use super::{FdEntry, Process, StatusCode};
-use crate::os::{FsEntry, FsError, ProcessInner};
+use super::{
+    Descriptor, Deterministic, Device, FdEntry, Inode, Process, Signal, StatusCode, channel,
+    signal_name,
+};
+use crate::os::{Clock, FsEntry, FsError, ProcessInner, Times};
 
 type Addr = u32;
 type Size = u32;
@@ -19,11 +23,8 @@ type FileSize = u64;
 type Timestamp = u64;
 type Fd = u32;
 type Advice = u8;
-type FstFlags = u16;
 type FileDelta = i64;
 type ExitCode = u32;
-type Signal = u8;
-type LookupFlags = u32;
 
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -44,7 +45,7 @@ enum ClockId {
 
 #[derive(Debug, Clone, Copy, Immutable, IntoBytes)]
 #[repr(transparent)]
-struct Rights(u64);
+pub struct Rights(u64);
 
 bitflags! {
     impl Rights: u64 {
@@ -81,6 +82,55 @@ bitflags! {
     }
 }
 
+impl Rights {
+    /// Rights of descriptors that read and write data.
+    const FILE: Rights = Rights::FD_DATASYNC
+        .union(Rights::FD_READ)
+        .union(Rights::FD_SEEK)
+        .union(Rights::FD_FDSTAT_SET_FLAGS)
+        .union(Rights::FD_SYNC)
+        .union(Rights::FD_TELL)
+        .union(Rights::FD_WRITE)
+        .union(Rights::FD_ADVISE)
+        .union(Rights::FD_ALLOCATE)
+        .union(Rights::FD_FILESTAT_GET)
+        .union(Rights::FD_FILESTAT_SET_SIZE)
+        .union(Rights::FD_FILESTAT_SET_TIMES)
+        .union(Rights::POLL_FD_READWRITE);
+
+    /// Rights of directory descriptors.
+    const DIR: Rights = Rights::PATH_CREATE_DIRECTORY
+        .union(Rights::PATH_CREATE_FILE)
+        .union(Rights::PATH_LINK_SOURCE)
+        .union(Rights::PATH_LINK_TARGET)
+        .union(Rights::PATH_OPEN)
+        .union(Rights::PATH_READDIR)
+        .union(Rights::PATH_READLINK)
+        .union(Rights::PATH_RENAME_SOURCE)
+        .union(Rights::PATH_RENAME_TARGET)
+        .union(Rights::PATH_FILESTAT_GET)
+        .union(Rights::PATH_FILESTAT_SET_SIZE)
+        .union(Rights::PATH_FILESTAT_SET_TIMES)
+        .union(Rights::FD_FILESTAT_GET)
+        .union(Rights::FD_FILESTAT_SET_TIMES)
+        .union(Rights::PATH_SYMLINK)
+        .union(Rights::PATH_REMOVE_DIRECTORY)
+        .union(Rights::PATH_UNLINK_FILE);
+
+    /// Returns the base and inheriting rights that apply to `entry`. Pipes and write callbacks
+    /// cannot seek, which is also how wasi-libc tells that the latter are terminals.
+    pub fn for_entry(entry: &FdEntry) -> (Rights, Rights) {
+        let rights = match entry {
+            FdEntry::WriteFn(_) | FdEntry::Pipe(_) => {
+                Rights::FILE.difference(Rights::FD_SEEK | Rights::FD_TELL)
+            }
+            FdEntry::Data { .. } | FdEntry::File(..) | FdEntry::Device(_) => Rights::FILE,
+            FdEntry::Dir(_) => return (Rights::DIR, Rights::DIR | Rights::FILE),
+        };
+        (rights, rights)
+    }
+}
+
 #[derive(Debug, Clone, Copy, Immutable, IntoBytes)]
 #[repr(transparent)]
 struct FdFlags(u16);
@@ -95,6 +145,16 @@ bitflags! {
     }
 }
 
+#[derive(Debug, Clone, Copy, Immutable, IntoBytes)]
+#[repr(transparent)]
+struct LookupFlags(u32);
+
+bitflags! {
+    impl LookupFlags: u32 {
+        const SYMLINK_FOLLOW = 1 << 0;
+    }
+}
+
 #[derive(Debug, Clone, Copy, Immutable, IntoBytes)]
 #[repr(transparent)]
 struct OFlags(u16);
@@ -108,35 +168,63 @@ bitflags! {
     }
 }
 
+#[derive(Debug, Clone, Copy, Immutable, IntoBytes)]
+#[repr(transparent)]
+struct FstFlags(u16);
+
+bitflags! {
+    impl FstFlags: u16 {
+        const ATIM = 1 << 0;
+        const ATIM_NOW = 1 << 1;
+        const MTIM = 1 << 2;
+        const MTIM_NOW = 1 << 3;
+    }
+}
+
 #[repr(u16)]
 #[derive(Debug, Clone, Copy, IntoBytes, Immutable)]
-#[allow(dead_code)]
 #[non_exhaustive]
 enum Errno {
     Success = 0,
-    Acces = 2,
     Badf = 8,
     Exist = 20,
     Fault = 21,
+    Fbig = 22,
     Inval = 28,
     IsDir = 31,
+    Loop = 32,
     NoEnt = 44,
     NotDir = 54,
+    NotEmpty = 55,
     NotSock = 57,
     Perm = 63,
+    Pipe = 64,
+    Rofs = 69,
+    NotCapable = 76,
+}
+
+impl From<FsError> for Errno {
+    fn from(err: FsError) -> Self {
+        match err {
+            FsError::DoesNotExist => Errno::NoEnt,
+            FsError::NotDir => Errno::NotDir,
+            FsError::IsDir => Errno::IsDir,
+            FsError::Exist => Errno::Exist,
+            FsError::NotEmpty => Errno::NotEmpty,
+            FsError::Invalid => Errno::Inval,
+            FsError::Loop => Errno::Loop,
+            FsError::ReadOnly => Errno::Rofs,
+        }
+    }
 }
 
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, Immutable, IntoBytes)]
-#[allow(dead_code)]
 enum FileType {
     Unknown = 0,
-    BlockDevice = 1,
     CharacterDevice = 2,
     Directory = 3,
     RegularFile = 4,
-    SocketDgram = 5,
-    SocketStream = 6,
     SymbolicLink = 7,
 }
 
@@ -147,7 +235,7 @@ enum ProcMsg {
     RuntimeError { re: String },
 }
 
-pub fn handle_message(proc: Rc<Process>, channel: SharedArrayBuffer, msg: JsValue) {
+pub fn handle_message(proc: Rc<Process>, tid: u32, channel: SharedArrayBuffer, msg: JsValue) {
     let msg = msg
         .dyn_into::<MessageEvent>()
         .expect("message event expected")
@@ -156,26 +244,115 @@ pub fn handle_message(proc: Rc<Process>, channel: SharedArrayBuffer, msg: JsValu
         .expect("failed to deserialize WASI syscall message");
 
     spawn_local(async move {
-        let ret = handle_message_inner(&proc, msg).await;
+        // Sent by threads that wrote to an empty channel, without waiting for a result.
+        if matches!(&msg, ProcMsg::Syscall { kind, .. } if kind == "flush") {
+            flush_fast_writes(&proc).await;
+            return;
+        }
+        let reads_stdin = matches!(
+            &msg,
+            ProcMsg::Syscall { kind, args } if kind == "fd_read" && args.first() == Some(&0)
+        );
+        let thread = tid as usize - 1;
+        proc.inner.borrow_mut().thread_times[thread].enter_syscall();
+        flush_fast_writes(&proc).await;
+        unread_stdin(&proc, &channel);
+        let ret = handle_message_inner(&proc, tid, msg).await;
+        proc.inner.borrow_mut().thread_times[thread].leave_syscall();
         match ret {
             Some(Some(ret)) => {
+                if reads_stdin && ret == Errno::Success as i32 {
+                    read_ahead_stdin(&proc, &channel);
+                }
+                update_fast_fds(&proc);
                 let array = Int32Array::new(&channel);
-                Atomics::store(&array, 0, ret).expect("failed to store result in channel");
-                Atomics::notify(&array, 0).expect("failed to notify main thread about result");
+                Atomics::store(&array, channel::RESULT, ret)
+                    .expect("failed to store result in channel");
+                Atomics::notify(&array, channel::RESULT)
+                    .expect("failed to notify main thread about result");
             }
             Some(None) => {}
             None => {
-                proc.kill(StatusCode::RuntimeError("invalid syscall".into()));
+                proc.kill(StatusCode::Trapped(Trap::Other("invalid syscall".into())));
             }
         }
     });
 }
 
-async fn handle_message_inner(proc: &Rc<Process>, msg: ProcMsg) -> Option<Option<i32>> {
+/// Performs the writes that threads made through their channels.
+async fn flush_fast_writes(proc: &Process) {
+    let _guard = proc.fast_write_lock.lock().await;
+    let channels = proc.inner.borrow().channels.clone();
+    for channel in channels {
+        for (fd, data) in channel::take_writes(&channel) {
+            // Output written after the process terminated is discarded.
+            if proc.inner.borrow().end_instant.is_some() {
+                return;
+            }
+            let _ = write_fd(proc, fd, &data).await;
+        }
+    }
+}
+
+/// Tells threads whether they may write to stdout and stderr through their channels, which
+/// is only the case for files that writes cannot fail or wait on. Pipes are excluded, so that
+/// writers wait for room and see `EPIPE` as soon as the reader is gone.
+pub fn update_fast_fds(proc: &Process) {
+    let inner = proc.inner.borrow();
+    let flags = Int32Array::new(&proc.fast_fds);
+    for fd in 1..=2 {
+        let fast = matches!(
+            get_fd(&inner.fds, fd, Rights::FD_WRITE),
+            Ok(Descriptor {
+                entry: FdEntry::WriteFn(_) | FdEntry::Data { .. },
+                ..
+            })
+        );
+        Atomics::store(&flags, fd, fast as i32).expect("failed to store fast fd flag");
+    }
+}
+
+/// Moves data already available on a stdin pipe into the read-ahead buffer of the thread, so
+/// that it can read it without waiting for the host. This is only done for single-threaded
+/// processes, as other threads could not read that data.
+fn read_ahead_stdin(proc: &Process, channel: &SharedArrayBuffer) {
+    let inner = proc.inner.borrow();
+    if inner.thread_times.len() != 1 {
+        return;
+    }
+    let Ok(Descriptor {
+        entry: FdEntry::Pipe(pipe),
+        ..
+    }) = get_fd(&inner.fds, 0, Rights::FD_READ)
+    else {
+        return;
+    };
+    let mut buf = vec![0; channel::READ_CAPACITY as usize];
+    let len = pipe.try_read(&mut buf);
+    channel::set_read_ahead(channel, &buf[..len]);
+}
+
+/// Moves the data the thread has not read from its read-ahead buffer back into the stdin pipe,
+/// so that the syscall being handled sees it.
+fn unread_stdin(proc: &Process, channel: &SharedArrayBuffer) {
+    let data = channel::take_read_ahead(channel);
+    if data.is_empty() {
+        return;
+    }
+    if let Some(Some(Descriptor {
+        entry: FdEntry::Pipe(pipe),
+        ..
+    })) = proc.inner.borrow().fds.first()
+    {
+        pipe.unread(&data);
+    }
+}
+
+async fn handle_message_inner(proc: &Rc<Process>, tid: u32, msg: ProcMsg) -> Option<Option<i32>> {
     let (kind, args) = match msg {
         ProcMsg::Syscall { kind, args } => (kind, args),
         ProcMsg::RuntimeError { re } => {
-            proc.kill(StatusCode::RuntimeError(re));
+            proc.kill(StatusCode::from_trap(re));
             return Some(None);
         }
     };
@@ -245,13 +422,25 @@ async fn handle_message_inner(proc: &Rc<Process>, msg: ProcMsg) -> Option<Option
         }
     }
 
+    impl Arg<LookupFlags> for i64 {
+        fn a(self) -> Option<LookupFlags> {
+            Some(LookupFlags(self.a()?))
+        }
+    }
+
+    impl Arg<FstFlags> for i64 {
+        fn a(self) -> Option<FstFlags> {
+            Some(FstFlags(self.a()?))
+        }
+    }
+
     Some(Some(match (kind.as_str(), args.as_slice()) {
         ("args_get", &[a, b]) => args_get(proc, a.a()?, b.a()?) as _,
         ("args_sizes_get", &[a, b]) => args_sizes_get(proc, a.a()?, b.a()?) as _,
         ("environ_get", &[a, b]) => environ_get(proc, a.a()?, b.a()?) as _,
         ("environ_sizes_get", &[a, b]) => environ_sizes_get(proc, a.a()?, b.a()?) as _,
         ("clock_res_get", &[a, b]) => clock_res_get(proc, a.a()?, b.a()?) as _,
-        ("clock_time_get", &[a, b, c]) => clock_time_get(proc, a.a()?, b.a()?, c.a()?) as _,
+        ("clock_time_get", &[a, b, c]) => clock_time_get(proc, tid, a.a()?, b.a()?, c.a()?) as _,
         ("fd_advise", &[a, b, c, d]) => fd_advise(proc, a.a()?, b.a()?, c.a()?, d.a()?) as _,
         ("fd_allocate", &[a, b, c]) => fd_allocate(proc, a.a()?, b.a()?, c.a()?) as _,
         ("fd_close", &[a]) => fd_close(proc, a.a()?) as _,
@@ -284,7 +473,7 @@ async fn handle_message_inner(proc: &Rc<Process>, msg: ProcMsg) -> Option<Option
         ("fd_seek", &[a, b, c, d]) => fd_seek(proc, a.a()?, b.a()?, c.a()?, d.a()?) as _,
         ("fd_sync", &[a]) => fd_sync(proc, a.a()?) as _,
         ("fd_tell", &[a, b]) => fd_tell(proc, a.a()?, b.a()?) as _,
-        ("fd_write", &[a, b, c, d]) => fd_write(proc, a.a()?, b.a()?, c.a()?, d.a()?) as _,
+        ("fd_write", &[a, b, c, d]) => fd_write(proc, a.a()?, b.a()?, c.a()?, d.a()?).await as _,
         ("path_create_directory", &[a, b, c]) => {
             path_create_directory(proc, a.a()?, b.a()?, c.a()?) as _
         }
@@ -339,9 +528,13 @@ async fn handle_message_inner(proc: &Rc<Process>, msg: ProcMsg) -> Option<Option
         }
         ("sock_shutdown", &[a, b]) => sock_shutdown(proc, a.a()?, b.a()?) as _,
         ("poll_oneoff", &[a, b, c, d]) => {
-            poll_oneoff(proc, a.a()?, b.a()?, c.a()?, d.a()?).await as _
+            poll_oneoff(proc, tid, a.a()?, b.a()?, c.a()?, d.a()?).await as _
         }
         ("thread_spawn", &[a]) => thread_spawn(proc, a.a()?),
+        ("thread_exit", &[]) => {
+            thread_exit(proc, tid);
+            return Some(None);
+        }
         _ => return None,
     }))
 }
@@ -371,6 +564,14 @@ struct FileStatT {
     ctim: Timestamp,
 }
 
+impl FileStatT {
+    fn set_times(&mut self, times: Times) {
+        self.atim = times.atim;
+        self.mtim = times.mtim;
+        self.ctim = times.ctim;
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, IntoBytes, FromBytes)]
 struct IoVecT {
@@ -418,6 +619,46 @@ fn read_from_mem<T: FromBytes + IntoBytes + ?Sized>(
     Ok(())
 }
 
+/// Returns the descriptor open as `fd`, failing with `NOTCAPABLE` unless it has all of `rights`.
+fn get_fd(fds: &[Option<Descriptor>], fd: Fd, rights: Rights) -> Result<&Descriptor, Errno> {
+    let Some(Some(descriptor)) = fds.get(fd as usize) else {
+        return Err(Errno::Badf);
+    };
+    if !descriptor.rights.contains(rights) {
+        return Err(Errno::NotCapable);
+    }
+    Ok(descriptor)
+}
+
+fn get_fd_mut(
+    fds: &mut [Option<Descriptor>],
+    fd: Fd,
+    rights: Rights,
+) -> Result<&mut Descriptor, Errno> {
+    let Some(Some(descriptor)) = fds.get_mut(fd as usize) else {
+        return Err(Errno::Badf);
+    };
+    if !descriptor.rights.contains(rights) {
+        return Err(Errno::NotCapable);
+    }
+    Ok(descriptor)
+}
+
+/// Returns the inode of the directory open as `fd`, which paths are resolved from, failing with
+/// `NOTCAPABLE` unless the descriptor has all of `rights`.
+fn get_dir(fds: &[Option<Descriptor>], fd: Fd, rights: Rights) -> Result<Inode, Errno> {
+    let Some(Some(descriptor)) = fds.get(fd as usize) else {
+        return Err(Errno::Badf);
+    };
+    let FdEntry::Dir(inode) = descriptor.entry else {
+        return Err(Errno::Badf);
+    };
+    if !descriptor.rights.contains(rights) {
+        return Err(Errno::NotCapable);
+    }
+    Ok(inode)
+}
+
 fn args_get(proc: &Process, argv: Addr, argv_buf: Addr) -> Errno {
     let mut argv_vec = vec![0; proc.args.len()];
     let mut offset = 0;
@@ -482,8 +723,8 @@ fn clock_res_get(proc: &Process, clock_id: ClockId, out: Addr) -> Errno {
     let prec: Timestamp = match clock_id {
         ClockId::Monotonic => 1,
         ClockId::Realtime => 1,
-        ClockId::ProcessCpu => return Errno::Inval,
-        ClockId::ThreadCpu => return Errno::Inval,
+        ClockId::ProcessCpu => 1,
+        ClockId::ThreadCpu => 1,
     };
     if let Err(e) = write_to_mem(proc, out, &prec) {
         return e;
@@ -491,13 +732,24 @@ fn clock_res_get(proc: &Process, clock_id: ClockId, out: Addr) -> Errno {
     Errno::Success
 }
 
-fn clock_time_get(proc: &Process, clock_id: ClockId, _precision: Timestamp, time: Addr) -> Errno {
-    let val = match clock_id {
-        ClockId::Realtime => web_time::UNIX_EPOCH.elapsed().unwrap().as_nanos() as Timestamp,
-        ClockId::Monotonic => proc.start_instant.elapsed().as_nanos() as Timestamp,
-        ClockId::ProcessCpu => unimplemented!(),
-        ClockId::ThreadCpu => unimplemented!(),
+fn clock_now(proc: &Process, tid: u32, clock_id: ClockId) -> Timestamp {
+    let time = match clock_id {
+        ClockId::Realtime => return proc.inner.borrow().fs.clock().now(),
+        ClockId::Monotonic => proc.start_instant.elapsed(),
+        ClockId::ProcessCpu => proc.inner.borrow().cpu_time(),
+        ClockId::ThreadCpu => proc.inner.borrow().thread_times[tid as usize - 1].cpu_time(),
     };
+    time.as_nanos() as Timestamp
+}
+
+fn clock_time_get(
+    proc: &Process,
+    tid: u32,
+    clock_id: ClockId,
+    _precision: Timestamp,
+    time: Addr,
+) -> Errno {
+    let val = clock_now(proc, tid, clock_id);
     if let Err(e) = write_to_mem(proc, time, &val) {
         return e;
     }
@@ -515,8 +767,74 @@ fn fd_advise(
     Errno::Success
 }
 
-fn fd_allocate(_proc: &Process, _fd: Fd, _offset: FileSize, _len: FileSize) -> Errno {
-    Errno::Perm
+/// Files are kept in a single host buffer, which cannot grow past the wasm32 address space.
+const MAX_FILE_SIZE: FileSize = u32::MAX as FileSize;
+
+/// Copies the contents of a file at `offset` into `buf`, returning the number of bytes copied.
+/// Another descriptor may have truncated the file, so `offset` can be past its end.
+fn read_at(data: &[u8], offset: FileSize, buf: &mut [u8]) -> usize {
+    let data = usize::try_from(offset)
+        .ok()
+        .and_then(|offset| data.get(offset..))
+        .unwrap_or(&[]);
+    let len = data.len().min(buf.len());
+    buf[..len].copy_from_slice(&data[..len]);
+    len
+}
+
+/// Writes `buf` at `offset` in a file, filling any gap after its end with zeros, and returns the
+/// offset just past the written data.
+fn write_at(data: &mut Vec<u8>, offset: FileSize, buf: &[u8]) -> Result<usize, Errno> {
+    let end = offset
+        .checked_add(buf.len() as FileSize)
+        .filter(|&end| end <= MAX_FILE_SIZE)
+        .ok_or(Errno::Fbig)? as usize;
+    let offset = offset as usize;
+    if data.len() < end {
+        data.resize(end, 0);
+    }
+    data[offset..end].copy_from_slice(buf);
+    Ok(end)
+}
+
+/// Sets the size of the file open as `fd`, which needs `rights`. If `grow_only`, the file is
+/// never shrunk.
+fn set_fd_size(proc: &Process, fd: Fd, rights: Rights, size: FileSize, grow_only: bool) -> Errno {
+    if size > MAX_FILE_SIZE {
+        return Errno::Fbig;
+    }
+    let size = size as usize;
+    let mut proc_inner = proc.inner.borrow_mut();
+    let ProcessInner { fds, fs, .. } = &mut *proc_inner;
+    let fd_entry = match get_fd_mut(fds, fd, rights) {
+        Ok(descriptor) => &mut descriptor.entry,
+        Err(e) => return e,
+    };
+    match fd_entry {
+        FdEntry::Data { data, .. } => {
+            if !grow_only || size > data.len() {
+                data.resize(size, 0);
+            }
+        }
+        FdEntry::File(inode, _, _) => {
+            let len = fs.entry(*inode).as_file().unwrap().len();
+            if (!grow_only || size > len)
+                && let Err(e) = fs.set_file_size(*inode, size)
+            {
+                return e.into();
+            }
+        }
+        FdEntry::Dir(_) => return Errno::IsDir,
+        FdEntry::WriteFn(_) | FdEntry::Pipe(_) | FdEntry::Device(_) => return Errno::Inval,
+    }
+    Errno::Success
+}
+
+fn fd_allocate(proc: &Process, fd: Fd, offset: FileSize, len: FileSize) -> Errno {
+    let Some(size) = offset.checked_add(len) else {
+        return Errno::Fbig;
+    };
+    set_fd_size(proc, fd, Rights::FD_ALLOCATE, size, true)
 }
 
 fn fd_close(proc: &Process, fd: Fd) -> Errno {
@@ -525,6 +843,7 @@ fn fd_close(proc: &Process, fd: Fd) -> Errno {
     if fd_entry.flatten().is_none() {
         return Errno::Badf;
     }
+    proc_inner.release_orphans();
     Errno::Success
 }
 
@@ -532,58 +851,30 @@ fn fd_datasync(_proc: &Process, _fd: Fd) -> Errno {
     Errno::Success
 }
 
-fn fd_fdstat_get(proc: &Process, fd: Fd, buf: Addr) -> Errno {
-    let proc_inner = proc.inner.borrow();
-    let Some(Some(file_info)) = proc_inner.fds.get(fd as usize) else {
-        return Errno::Badf;
+fn fdstat(descriptor: &Descriptor) -> FdStatT {
+    let (filetype, flags) = match descriptor.entry {
+        FdEntry::WriteFn(_) => (FileType::CharacterDevice, FdFlags::APPEND),
+        FdEntry::Data { .. } | FdEntry::File(..) => (FileType::RegularFile, FdFlags::empty()),
+        FdEntry::Dir(_) => (FileType::Directory, FdFlags::empty()),
+        FdEntry::Pipe(_) => (FileType::Unknown, FdFlags::APPEND),
+        FdEntry::Device(_) => (FileType::CharacterDevice, FdFlags::empty()),
     };
-    let mut fdstat = FdStatT {
-        fs_filetype: FileType::Unknown,
+    FdStatT {
+        fs_filetype: filetype,
         _pad1: [0; 1],
-        fs_flags: FdFlags::empty(),
+        fs_flags: flags,
         _pad2: [0; 4],
-        fs_rights_base: Rights::empty(),
-        fs_rights_inheriting: Rights::empty(),
-    };
-    match file_info {
-        FdEntry::WriteFn(_) => {
-            fdstat.fs_filetype = FileType::CharacterDevice;
-            fdstat.fs_flags = FdFlags::APPEND;
-            fdstat.fs_rights_base = Rights::FD_WRITE;
-            fdstat.fs_rights_inheriting = Rights::FD_WRITE;
-        }
-        FdEntry::Data { .. } => {
-            fdstat.fs_filetype = FileType::RegularFile;
-            fdstat.fs_rights_base = Rights::FD_READ | Rights::FD_WRITE | Rights::FD_SEEK;
-            fdstat.fs_rights_inheriting = Rights::FD_READ | Rights::FD_WRITE | Rights::FD_SEEK;
-        }
-        FdEntry::Dir(_) => {
-            fdstat.fs_filetype = FileType::Directory;
-            fdstat.fs_rights_base = Rights::PATH_OPEN
-                | Rights::PATH_FILESTAT_GET
-                | Rights::FD_FILESTAT_GET
-                | Rights::PATH_READDIR;
-            fdstat.fs_rights_inheriting = Rights::FD_READ
-                | Rights::PATH_OPEN
-                | Rights::PATH_FILESTAT_GET
-                | Rights::FD_FILESTAT_GET
-                | Rights::FD_SEEK
-                | Rights::PATH_READDIR;
-        }
-        FdEntry::File(_, _, _) => {
-            fdstat.fs_filetype = FileType::RegularFile;
-            fdstat.fs_rights_base = Rights::FD_READ | Rights::FD_SEEK | Rights::FD_FILESTAT_GET;
-            fdstat.fs_rights_inheriting =
-                Rights::FD_READ | Rights::FD_WRITE | Rights::FD_FILESTAT_GET;
-        }
-        FdEntry::Pipe(_) => {
-            fdstat.fs_filetype = FileType::Unknown;
-            fdstat.fs_flags = FdFlags::APPEND;
-            fdstat.fs_rights_base = Rights::FD_READ | Rights::FD_WRITE;
-            fdstat.fs_rights_inheriting = Rights::FD_READ | Rights::FD_WRITE;
-        }
+        fs_rights_base: descriptor.rights,
+        fs_rights_inheriting: descriptor.rights_inheriting,
+    }
+}
+
+fn fd_fdstat_get(proc: &Process, fd: Fd, buf: Addr) -> Errno {
+    let proc_inner = proc.inner.borrow();
+    let Some(Some(descriptor)) = proc_inner.fds.get(fd as usize) else {
+        return Errno::Badf;
     };
-    if let Err(e) = write_to_mem(proc, buf, &fdstat) {
+    if let Err(e) = write_to_mem(proc, buf, &fdstat(descriptor)) {
         return e;
     }
     Errno::Success
@@ -594,15 +885,25 @@ fn fd_fdstat_set_flags(_proc: &Process, _fd: Fd, _flags: FdFlags) -> Errno {
     Errno::Success
 }
 
-fn fd_fdstat_set_rights(_proc: &Process, _fd: Fd, _base: Rights, _inheriting: Rights) -> Errno {
-    // TODO ?
+fn fd_fdstat_set_rights(proc: &Process, fd: Fd, base: Rights, inheriting: Rights) -> Errno {
+    let mut proc_inner = proc.inner.borrow_mut();
+    let Some(Some(descriptor)) = proc_inner.fds.get_mut(fd as usize) else {
+        return Errno::Badf;
+    };
+    // Rights can only be dropped.
+    if !descriptor.rights.contains(base) || !descriptor.rights_inheriting.contains(inheriting) {
+        return Errno::NotCapable;
+    }
+    descriptor.rights = base;
+    descriptor.rights_inheriting = inheriting;
     Errno::Success
 }
 
 fn fd_filestat_get(proc: &Process, fd: Fd, out: Addr) -> Errno {
     let proc_inner = proc.inner.borrow();
-    let Some(Some(file_info)) = proc_inner.fds.get(fd as usize) else {
-        return Errno::Badf;
+    let file_info = match get_fd(&proc_inner.fds, fd, Rights::FD_FILESTAT_GET) {
+        Ok(descriptor) => &descriptor.entry,
+        Err(e) => return e,
     };
     let mut fstat = FileStatT {
         dev: 0,
@@ -632,15 +933,19 @@ fn fd_filestat_get(proc: &Process, fd: Fd, out: Addr) -> Errno {
         FdEntry::File(inode, _, _) => {
             fstat.filetype = FileType::RegularFile;
             fstat.inode = *inode;
-            fstat.size = proc_inner.fs.entries[*inode as usize]
-                .as_file()
-                .unwrap()
-                .len() as FileSize;
+            fstat.size = proc_inner.fs.entry(*inode).as_file().unwrap().len() as FileSize;
         }
         FdEntry::Pipe(_) => {
             fstat.dev = 1;
             fstat.filetype = FileType::Unknown;
         }
+        FdEntry::Device(_) => {
+            fstat.dev = 1;
+            fstat.filetype = FileType::CharacterDevice;
+        }
+    }
+    if let FdEntry::Dir(inode) | FdEntry::File(inode, _, _) = file_info {
+        fstat.set_times(proc_inner.fs.times(*inode));
     }
     if let Err(e) = write_to_mem(proc, out, &fstat) {
         return e;
@@ -648,18 +953,68 @@ fn fd_filestat_get(proc: &Process, fd: Fd, out: Addr) -> Errno {
     Errno::Success
 }
 
-fn fd_filestat_set_size(_proc: &Process, _fd: Fd, _size: FileSize) -> Errno {
-    Errno::Perm
+fn fd_filestat_set_size(proc: &Process, fd: Fd, size: FileSize) -> Errno {
+    set_fd_size(proc, fd, Rights::FD_FILESTAT_SET_SIZE, size, false)
+}
+
+/// Computes the new access and modification times requested by `fst_flags`, taking the current
+/// time from `clock`.
+fn requested_times(
+    clock: &Clock,
+    atim: Timestamp,
+    mtim: Timestamp,
+    fst_flags: FstFlags,
+) -> Result<(Option<Timestamp>, Option<Timestamp>), Errno> {
+    if fst_flags.contains(FstFlags::ATIM | FstFlags::ATIM_NOW)
+        || fst_flags.contains(FstFlags::MTIM | FstFlags::MTIM_NOW)
+    {
+        return Err(Errno::Inval);
+    }
+    let now = clock.now();
+    let pick = |time, set, set_now| {
+        if fst_flags.contains(set) {
+            Some(time)
+        } else if fst_flags.contains(set_now) {
+            Some(now)
+        } else {
+            None
+        }
+    };
+    Ok((
+        pick(atim, FstFlags::ATIM, FstFlags::ATIM_NOW),
+        pick(mtim, FstFlags::MTIM, FstFlags::MTIM_NOW),
+    ))
 }
 
 fn fd_filestat_set_times(
-    _proc: &Process,
-    _fd: Fd,
-    _atim: Timestamp,
-    _mtim: Timestamp,
-    _fst_flags: FstFlags,
+    proc: &Process,
+    fd: Fd,
+    atim: Timestamp,
+    mtim: Timestamp,
+    fst_flags: FstFlags,
 ) -> Errno {
-    Errno::Perm
+    let mut proc_inner = proc.inner.borrow_mut();
+    let ProcessInner { fds, fs, .. } = &mut *proc_inner;
+    let fd_entry = match get_fd(fds, fd, Rights::FD_FILESTAT_SET_TIMES) {
+        Ok(descriptor) => &descriptor.entry,
+        Err(e) => return e,
+    };
+    let (atim, mtim) = match requested_times(fs.clock(), atim, mtim, fst_flags) {
+        Ok(times) => times,
+        Err(e) => return e,
+    };
+    match fd_entry {
+        FdEntry::Dir(inode) | FdEntry::File(inode, _, _) => {
+            match fs.set_times(*inode, atim, mtim) {
+                Ok(()) => Errno::Success,
+                Err(e) => e.into(),
+            }
+        }
+        // Not backed by an inode: there is nowhere to store the times.
+        FdEntry::WriteFn(_) | FdEntry::Data { .. } | FdEntry::Pipe(_) | FdEntry::Device(_) => {
+            Errno::Success
+        }
+    }
 }
 
 fn fd_pread(
@@ -671,8 +1026,9 @@ fn fd_pread(
     result: Addr,
 ) -> Errno {
     let proc_inner = proc.inner.borrow();
-    let Some(Some(file_entry)) = proc_inner.fds.get(fd as usize) else {
-        return Errno::Badf;
+    let file_entry = match get_fd(&proc_inner.fds, fd, Rights::FD_READ | Rights::FD_SEEK) {
+        Ok(descriptor) => &descriptor.entry,
+        Err(e) => return e,
     };
     let mut iovs = vec![IoVecT { buf: 0, buf_len: 0 }; buf_len as usize];
     if let Err(e) = read_from_mem(proc, buf, &mut iovs[..]) {
@@ -680,22 +1036,15 @@ fn fd_pread(
     }
     let mut in_data = vec![0u8; iovs.iter().map(|iov| iov.buf_len).sum::<Size>() as usize];
     let read = match file_entry {
-        FdEntry::Data { data, .. } => {
-            let data = &data[offset as usize..];
-            let read_len = data.len().min(in_data.len());
-            in_data[..read_len].copy_from_slice(&data[..read_len]);
-            read_len
-        }
+        FdEntry::Data { data, .. } => read_at(data, offset, &mut in_data),
         FdEntry::File(inode, _, _) => {
-            let data = proc_inner.fs.entries[*inode as usize].as_file().unwrap();
-            let data = &data[offset as usize..];
-            let read_len = data.len().min(in_data.len());
-            in_data[..read_len].copy_from_slice(&data[..read_len]);
-            read_len
+            let data = proc_inner.fs.entry(*inode).as_file().unwrap();
+            read_at(data, offset, &mut in_data)
         }
         FdEntry::WriteFn(_) => return Errno::Badf,
         FdEntry::Dir(_) => return Errno::Badf,
         FdEntry::Pipe(_) => return Errno::Badf,
+        FdEntry::Device(_) => return Errno::Badf,
     };
     let mut pos = 0;
     for IoVecT { buf, buf_len } in iovs {
@@ -712,13 +1061,16 @@ fn fd_pread(
 
 fn fd_prestat_get(proc: &Process, fd: Fd, out: Addr) -> Errno {
     let proc_inner = proc.inner.borrow();
-    let Some(Some(file_entry)) = proc_inner.fds.get(fd as usize) else {
+    let Some(Some(descriptor)) = proc_inner.fds.get(fd as usize) else {
         return Errno::Badf;
     };
-    let FdEntry::Dir(inode) = *file_entry else {
+    let FdEntry::Dir(inode) = descriptor.entry else {
         return Errno::Badf;
     };
-    let name = proc_inner.fs.get_name(inode);
+    let name = match proc_inner.fs.get_name(inode) {
+        Ok(name) => name,
+        Err(e) => return e.into(),
+    };
     let prestat = PreStatT {
         tag: 0,
         pad: [0; 3],
@@ -732,13 +1084,16 @@ fn fd_prestat_get(proc: &Process, fd: Fd, out: Addr) -> Errno {
 
 fn fd_prestat_dir_name(proc: &Process, fd: Fd, path: Addr, _path_len: Size) -> Errno {
     let proc_inner = proc.inner.borrow();
-    let Some(Some(file_entry)) = proc_inner.fds.get(fd as usize) else {
+    let Some(Some(descriptor)) = proc_inner.fds.get(fd as usize) else {
         return Errno::Badf;
     };
-    let FdEntry::Dir(inode) = *file_entry else {
+    let FdEntry::Dir(inode) = descriptor.entry else {
         return Errno::Badf;
     };
-    let name = proc_inner.fs.get_name(inode);
+    let name = match proc_inner.fs.get_name(inode) {
+        Ok(name) => name,
+        Err(e) => return e.into(),
+    };
     if let Err(e) = write_to_mem(proc, path, name.as_slice()) {
         return e;
     }
@@ -755,8 +1110,9 @@ fn fd_pwrite(
 ) -> Errno {
     let mut proc_inner = proc.inner.borrow_mut();
     let ProcessInner { fds, fs, .. } = &mut *proc_inner;
-    let Some(Some(fd_entry)) = fds.get_mut(fd as usize) else {
-        return Errno::Badf;
+    let fd_entry = match get_fd_mut(fds, fd, Rights::FD_WRITE | Rights::FD_SEEK) {
+        Ok(descriptor) => &mut descriptor.entry,
+        Err(e) => return e,
     };
     let mut iovs = vec![IoVecT { buf: 0, buf_len: 0 }; iovs_len as usize];
     if let Err(e) = read_from_mem(proc, iovs_addr, &mut iovs[..]) {
@@ -773,26 +1129,26 @@ fn fd_pwrite(
     }
     let written = match fd_entry {
         FdEntry::Data { data, .. } => {
-            let end = offset as usize + in_data.len();
-            if end > data.len() {
-                data.resize(end, 0);
+            if let Err(e) = write_at(data, offset, &in_data) {
+                return e;
             }
-            data[offset as usize..end].copy_from_slice(&in_data);
             in_data.len()
         }
         FdEntry::File(inode, _, _) => {
-            let file_entry = fs.entries[*inode as usize].as_file_mut().unwrap();
-            let data = Rc::make_mut(file_entry);
-            let end = offset as usize + in_data.len();
-            if end > data.len() {
-                data.resize(end, 0);
+            if fs.is_read_only(*inode) {
+                return Errno::Rofs;
+            }
+            let file_entry = fs.entry_mut(*inode).as_file_mut().unwrap();
+            if let Err(e) = write_at(Rc::make_mut(file_entry), offset, &in_data) {
+                return e;
             }
-            data[offset as usize..end].copy_from_slice(&in_data);
+            fs.touch(*inode);
             in_data.len()
         }
         FdEntry::WriteFn(_) => return Errno::Badf,
         FdEntry::Pipe(_) => return Errno::Badf,
         FdEntry::Dir(_) => return Errno::Badf,
+        FdEntry::Device(_) => return Errno::Badf,
     };
     if let Err(e) = write_to_mem(proc, out, &written) {
         return e;
@@ -809,23 +1165,25 @@ async fn fd_read(proc: &Process, fd: Fd, buf: Addr, buf_len: Size, result: Addr)
     let mut pipe = None;
     let mut read = {
         let mut proc_inner = proc.inner.borrow_mut();
-        let ProcessInner { fds, fs, .. } = &mut *proc_inner;
-        let Some(Some(file_entry)) = fds.get_mut(fd as usize) else {
-            return Errno::Badf;
+        let ProcessInner {
+            fds,
+            fs,
+            deterministic,
+            ..
+        } = &mut *proc_inner;
+        let file_entry = match get_fd_mut(fds, fd, Rights::FD_READ) {
+            Ok(descriptor) => &mut descriptor.entry,
+            Err(e) => return e,
         };
         match file_entry {
             FdEntry::Data { data, offset } => {
-                let data = &data[*offset..];
-                let read_len = data.len().min(in_data.len());
-                in_data[..read_len].copy_from_slice(&data[..read_len]);
+                let read_len = read_at(data, *offset as FileSize, &mut in_data);
                 *offset += read_len;
                 read_len
             }
             FdEntry::File(inode, offset, _) => {
-                let data = fs.entries[*inode as usize].as_file().unwrap();
-                let data = &data[*offset..];
-                let read_len = data.len().min(in_data.len());
-                in_data[..read_len].copy_from_slice(&data[..read_len]);
+                let data = fs.entry(*inode).as_file().unwrap();
+                let read_len = read_at(data, *offset as FileSize, &mut in_data);
                 *offset += read_len;
                 read_len
             }
@@ -833,6 +1191,7 @@ async fn fd_read(proc: &Process, fd: Fd, buf: Addr, buf_len: Size, result: Addr)
                 pipe = Some(p.clone());
                 0
             }
+            FdEntry::Device(device) => read_device(*device, deterministic, &mut in_data),
             FdEntry::WriteFn(_) => return Errno::Badf,
             FdEntry::Dir(_) => return Errno::Badf,
         }
@@ -862,14 +1221,12 @@ fn fd_readdir(
     out: Addr,
 ) -> Errno {
     let proc_inner = proc.inner.borrow();
-    let Some(Some(fd_entry)) = proc_inner.fds.get(fd as usize) else {
-        return Errno::Badf;
-    };
-    let FdEntry::Dir(dir_inode) = *fd_entry else {
-        return Errno::Badf;
+    let dir_inode = match get_dir(&proc_inner.fds, fd, Rights::PATH_READDIR) {
+        Ok(inode) => inode,
+        Err(e) => return e,
     };
     let mut buf = Vec::new();
-    let entries = proc_inner.fs.entries[dir_inode as usize].as_dir().unwrap();
+    let entries = proc_inner.fs.entry(dir_inode).as_dir().unwrap();
     for (idx, (name, inode)) in entries.iter().enumerate().skip(cookie as usize) {
         if buf.len() >= buf_len as usize {
             break;
@@ -877,10 +1234,12 @@ fn fd_readdir(
         buf.extend_from_slice(&(idx as u64 + 1).to_le_bytes());
         buf.extend_from_slice(&inode.to_le_bytes());
         buf.extend_from_slice(&(name.len() as u32).to_le_bytes());
-        let file_type = match proc_inner.fs.entries[*inode as usize] {
+        let file_type = match proc_inner.fs.entry(*inode) {
             FsEntry::File(_) => FileType::RegularFile,
             FsEntry::Dir(_) => FileType::Directory,
             FsEntry::Pipe(_) => FileType::Unknown,
+            FsEntry::Device(_) => FileType::CharacterDevice,
+            FsEntry::Symlink(_) => FileType::SymbolicLink,
         };
         buf.extend_from_slice(&(file_type as u32).to_le_bytes());
         buf.extend_from_slice(name);
@@ -895,23 +1254,49 @@ fn fd_readdir(
     Errno::Success
 }
 
-fn fd_renumber(_proc: &Process, _fd: Fd, _to_fd: Fd) -> Errno {
-    // Maybe?
-    Errno::Perm
+fn fd_renumber(proc: &Process, fd: Fd, to_fd: Fd) -> Errno {
+    let mut proc_inner = proc.inner.borrow_mut();
+    let fds = &mut proc_inner.fds;
+    if !matches!(fds.get(fd as usize), Some(Some(_)))
+        || !matches!(fds.get(to_fd as usize), Some(Some(_)))
+    {
+        return Errno::Badf;
+    }
+    if fd == to_fd {
+        return Errno::Success;
+    }
+    // The entry previously at `to_fd` is closed by being overwritten.
+    fds[to_fd as usize] = fds[fd as usize].take();
+    proc_inner.release_orphans();
+    Errno::Success
 }
 
 fn fd_seek(proc: &Process, fd: Fd, offset: FileDelta, whence: Whence, out: Addr) -> Errno {
     let mut proc_inner = proc.inner.borrow_mut();
     let ProcessInner { fds, fs, .. } = &mut *proc_inner;
-    let Some(Some(file_info)) = fds.get_mut(fd as usize) else {
-        return Errno::Badf;
+    // Only reading the current offset is allowed with `FD_TELL` alone.
+    let rights = if whence == Whence::Cur && offset == 0 {
+        Rights::FD_TELL
+    } else {
+        Rights::FD_SEEK
     };
+    let file_info = match get_fd_mut(fds, fd, rights) {
+        Ok(descriptor) => &mut descriptor.entry,
+        Err(e) => return e,
+    };
+    if let FdEntry::Device(_) = file_info {
+        // Devices have no position: seeking always succeeds and stays at the start.
+        if let Err(e) = write_to_mem(proc, out, &(0 as FileSize)) {
+            return e;
+        }
+        return Errno::Success;
+    }
     let mut base_off: FileSize = match (whence, &*file_info) {
         (Whence::Set, _) => 0,
         (Whence::Cur, _) => 0,
         (Whence::End, FdEntry::Data { data, .. }) => data.len() as FileSize,
         (Whence::End, FdEntry::File(inode, _, _)) => {
-            fs.entries[*inode as usize].as_file().unwrap().len() as FileSize
+            fs.entry(*inode).as_file().unwrap().len() as FileSize
         }
         _ => return Errno::Inval,
     };
@@ -921,6 +1306,7 @@ fn fd_seek(proc: &Process, fd: Fd, offset: FileDelta, whence: Whence, out: Addr)
         FdEntry::WriteFn(_) => return Errno::Badf,
         FdEntry::Dir(_) => return Errno::Badf,
         FdEntry::Pipe(_) => return Errno::Badf,
+        FdEntry::Device(_) => unreachable!(),
     };
     if whence == Whence::Cur {
         base_off = *foff as FileSize;
@@ -940,12 +1326,10 @@ fn fd_tell(proc: &Process, fd: Fd, out: Addr) -> Errno {
     fd_seek(proc, fd, 0, Whence::Cur, out)
 }
 
-fn fd_write(proc: &Process, fd: Fd, iovs_addr: Addr, iovs_len: Size, result: Addr) -> Errno {
-    let mut proc_inner = proc.inner.borrow_mut();
-    let ProcessInner { fds, fs, .. } = &mut *proc_inner;
-    let Some(Some(fd_entry)) = fds.get_mut(fd as usize) else {
-        return Errno::Badf;
-    };
+async fn fd_write(proc: &Process, fd: Fd, iovs_addr: Addr, iovs_len: Size, result: Addr) -> Errno {
+    if let Err(e) = get_fd(&proc.inner.borrow().fds, fd, Rights::FD_WRITE) {
+        return e;
+    }
     let mut iovs = vec![IoVecT { buf: 0, buf_len: 0 }; iovs_len as usize];
     if let Err(e) = read_from_mem(proc, iovs_addr, &mut iovs[..]) {
         return e;
@@ -959,50 +1343,79 @@ fn fd_write(proc: &Process, fd: Fd, iovs_addr: Addr, iovs_len: Size, result: Add
         }
         pos += buf_len as usize;
     }
-    let written = match fd_entry {
-        FdEntry::WriteFn(f) => f(&in_data),
-        FdEntry::Data { data, offset } => {
-            let end = *offset + in_data.len();
-            if end > data.len() {
-                data.resize(end, 0);
+    let written = match write_fd(proc, fd, &in_data).await {
+        Ok(written) => written,
+        Err(e) => return e,
+    };
+    if let Err(e) = write_to_mem(proc, result, &written) {
+        return e;
+    }
+    Errno::Success
+}
+
+/// Writes `in_data` to `fd`, returning the number of bytes written.
+async fn write_fd(proc: &Process, fd: Fd, in_data: &[u8]) -> Result<Size, Errno> {
+    let mut pipe = None;
+    let written = {
+        let mut proc_inner = proc.inner.borrow_mut();
+        let ProcessInner {
+            fds,
+            fs,
+            output_written,
+            ..
+        } = &mut *proc_inner;
+        let fd_entry = &mut get_fd_mut(fds, fd, Rights::FD_WRITE)?.entry;
+        if let FdEntry::WriteFn(_) | FdEntry::Pipe(_) = fd_entry
+            && let Some(output_limit) = proc.output_limit
+        {
+            *output_written += in_data.len() as u64;
+            if *output_written > output_limit {
+                drop(proc_inner);
+                proc.kill(StatusCode::OutputLimitExceeded);
+                return Err(Errno::Fbig);
             }
-            data[*offset..end].copy_from_slice(&in_data);
-            *offset = end;
-            in_data.len()
         }
-        FdEntry::Pipe(pipe) => {
-            pipe.write(&in_data);
-            in_data.len()
-        }
-        FdEntry::File(inode, offset, append) => {
-            let file_entry = fs.entries[*inode as usize].as_file_mut().unwrap();
-            let data = Rc::make_mut(file_entry);
-            if *append {
-                *offset = data.len();
+        match fd_entry {
+            FdEntry::WriteFn(f) => f(in_data),
+            FdEntry::Data { data, offset } => {
+                *offset = write_at(data, *offset as FileSize, in_data)?;
+                in_data.len()
             }
-            let end = *offset + in_data.len();
-            if end > data.len() {
-                data.resize(end, 0);
+            FdEntry::Pipe(p) => {
+                pipe = Some(p.clone());
+                0
             }
-            data[*offset..end].copy_from_slice(&in_data);
-            *offset = end;
-            in_data.len()
+            FdEntry::Device(_) => in_data.len(),
+            FdEntry::File(inode, offset, append) => {
+                if fs.is_read_only(*inode) {
+                    return Err(Errno::Rofs);
+                }
+                let file_entry = fs.entry_mut(*inode).as_file_mut().unwrap();
+                let data = Rc::make_mut(file_entry);
+                if *append {
+                    *offset = data.len();
+                }
+                *offset = write_at(data, *offset as FileSize, in_data)?;
+                fs.touch(*inode);
+                in_data.len()
+            }
+            FdEntry::Dir(_) => return Err(Errno::Badf),
         }
-        FdEntry::Dir(_) => return Errno::Badf,
     };
-    if let Err(e) = write_to_mem(proc, result, &written) {
-        return e;
+    if let Some(pipe) = pipe {
+        if pipe.write(in_data).await.is_err() {
+            return Err(Errno::Pipe);
+        }
+        return Ok(in_data.len() as Size);
     }
-    Errno::Success
+    Ok(written as Size)
 }
 
 fn path_create_directory(proc: &Process, fd: Fd, path_addr: Addr, path_len: Size) -> Errno {
     let mut proc_inner = proc.inner.borrow_mut();
-    let Some(Some(file_entry)) = proc_inner.fds.get_mut(fd as usize) else {
-        return Errno::Badf;
-    };
-    let FdEntry::Dir(base_inode) = *file_entry else {
-        return Errno::Badf;
+    let base_inode = match get_dir(&proc_inner.fds, fd, Rights::PATH_CREATE_DIRECTORY) {
+        Ok(inode) => inode,
+        Err(e) => return e,
     };
     let mut path = vec![0; path_len as usize];
     if let Err(e) = read_from_mem(proc, path_addr, &mut path[..]) {
@@ -1010,38 +1423,31 @@ fn path_create_directory(proc: &Process, fd: Fd, path_addr: Addr, path_len: Size
     }
     match proc_inner.fs.create_dir(base_inode, &path) {
         Ok(_) => Errno::Success,
-        Err(FsError::DoesNotExist) => Errno::NoEnt,
-        Err(FsError::NotDir) => Errno::NotDir,
-        Err(FsError::IsDir) => Errno::IsDir,
-        Err(FsError::Exist) => Errno::Exist,
+        Err(e) => e.into(),
     }
 }
 
 fn path_filestat_get(
     proc: &Process,
     fd: Fd,
-    _flags: LookupFlags,
+    flags: LookupFlags,
     path_addr: Addr,
     path_len: Size,
     filestat: Addr,
 ) -> Errno {
     let proc_inner = proc.inner.borrow();
-    let Some(Some(file_entry)) = proc_inner.fds.get(fd as usize) else {
-        return Errno::Badf;
-    };
-    let FdEntry::Dir(base_inode) = *file_entry else {
-        return Errno::Badf;
+    let base_inode = match get_dir(&proc_inner.fds, fd, Rights::PATH_FILESTAT_GET) {
+        Ok(inode) => inode,
+        Err(e) => return e,
     };
     let mut path = vec![0; path_len as usize];
     if let Err(e) = read_from_mem(proc, path_addr, &mut path[..]) {
         return e;
     }
-    let inode = match proc_inner.fs.get(base_inode, &path) {
+    let follow_symlinks = flags.contains(LookupFlags::SYMLINK_FOLLOW);
+    let inode = match proc_inner.fs.lookup(base_inode, &path, follow_symlinks) {
         Ok(inode) => inode,
-        Err(FsError::DoesNotExist) => return Errno::NoEnt,
-        Err(FsError::NotDir) => return Errno::NotDir,
-        Err(FsError::IsDir) => return Errno::IsDir,
-        Err(FsError::Exist) => return Errno::Exist,
+        Err(e) => return e.into(),
     };
     let mut fstat = FileStatT {
         dev: 0,
@@ -1054,18 +1460,26 @@ fn path_filestat_get(
         mtim: 0,
         ctim: 0,
     };
-    match proc_inner.fs.entries[inode as usize] {
+    match proc_inner.fs.entry(inode) {
         FsEntry::Dir(_) => {
             fstat.filetype = FileType::Directory;
         }
-        FsEntry::File(ref file) => {
+        FsEntry::File(file) => {
             fstat.filetype = FileType::RegularFile;
             fstat.size = file.len() as FileSize;
         }
         FsEntry::Pipe(_) => {
             fstat.filetype = FileType::Unknown;
         }
+        FsEntry::Device(_) => {
+            fstat.filetype = FileType::CharacterDevice;
+        }
+        FsEntry::Symlink(target) => {
+            fstat.filetype = FileType::SymbolicLink;
+            fstat.size = target.len() as FileSize;
+        }
     }
+    fstat.set_times(proc_inner.fs.times(inode));
     if let Err(e) = write_to_mem(proc, filestat, &fstat) {
         return e;
     }
@@ -1074,16 +1488,37 @@ fn path_filestat_get(
 
 #[allow(clippy::too_many_arguments)]
 fn path_filestat_set_times(
-    _proc: &Process,
-    _fd: Fd,
-    _flags: LookupFlags,
-    _path: Addr,
-    _path_len: Size,
-    _atim: Timestamp,
-    _mtim: Timestamp,
-    _fst_flags: FstFlags,
+    proc: &Process,
+    fd: Fd,
+    flags: LookupFlags,
+    path_addr: Addr,
+    path_len: Size,
+    atim: Timestamp,
+    mtim: Timestamp,
+    fst_flags: FstFlags,
 ) -> Errno {
-    Errno::Perm
+    let mut proc_inner = proc.inner.borrow_mut();
+    let base_inode = match get_dir(&proc_inner.fds, fd, Rights::PATH_FILESTAT_SET_TIMES) {
+        Ok(inode) => inode,
+        Err(e) => return e,
+    };
+    let mut path = vec![0; path_len as usize];
+    if let Err(e) = read_from_mem(proc, path_addr, &mut path[..]) {
+        return e;
+    }
+    let (atim, mtim) = match requested_times(proc_inner.fs.clock(), atim, mtim, fst_flags) {
+        Ok(times) => times,
+        Err(e) => return e,
+    };
+    let follow_symlinks = flags.contains(LookupFlags::SYMLINK_FOLLOW);
+    let inode = match proc_inner.fs.lookup(base_inode, &path, follow_symlinks) {
+        Ok(inode) => inode,
+        Err(e) => return e.into(),
+    };
+    match proc_inner.fs.set_times(inode, atim, mtim) {
+        Ok(()) => Errno::Success,
+        Err(e) => e.into(),
+    }
 }
 
 fn path_link(
@@ -1102,23 +1537,29 @@ fn path_link(
 fn path_open(
     proc: &Process,
     dirfd: Fd,
-    _dir_flags: LookupFlags,
+    dir_flags: LookupFlags,
     path_ptr: Addr,
     path_len: Size,
     oflags: OFlags,
-    _rights_base: Rights,
-    _rights_inheriting: Rights,
+    rights_base: Rights,
+    rights_inheriting: Rights,
     fd_flags: FdFlags,
     out: Addr,
 ) -> Errno {
     let mut proc_inner = proc.inner.borrow_mut();
     let ProcessInner { fds, fs, .. } = &mut *proc_inner;
-    let Some(Some(file_entry)) = fds.get_mut(dirfd as usize) else {
-        return Errno::Badf;
-    };
-    let FdEntry::Dir(base_inode) = *file_entry else {
-        return Errno::Badf;
+    let mut dir_rights = Rights::PATH_OPEN;
+    if oflags.contains(OFlags::CREAT) {
+        dir_rights |= Rights::PATH_CREATE_FILE;
+    }
+    if oflags.contains(OFlags::TRUNC) {
+        dir_rights |= Rights::PATH_FILESTAT_SET_SIZE;
+    }
+    let base_inode = match get_dir(fds, dirfd, dir_rights) {
+        Ok(inode) => inode,
+        Err(e) => return e,
     };
+    let dir_rights_inheriting = fds[dirfd as usize].as_ref().unwrap().rights_inheriting;
     let mut path = vec![0; path_len as usize];
     if let Err(e) = read_from_mem(proc, path_ptr, &mut path[..]) {
         return e;
@@ -1126,30 +1567,46 @@ fn path_open(
     let inode = match fs.open(
         base_inode,
         &path,
+        dir_flags.contains(LookupFlags::SYMLINK_FOLLOW),
         oflags.contains(OFlags::CREAT),
         oflags.contains(OFlags::EXCL),
     ) {
         Ok(inode) => inode,
-        Err(FsError::DoesNotExist) => return Errno::NoEnt,
-        Err(FsError::NotDir) => return Errno::NotDir,
-        Err(FsError::IsDir) => return Errno::IsDir,
-        Err(FsError::Exist) => return Errno::Exist,
+        Err(e) => return e.into(),
     };
-    if oflags.contains(OFlags::DIRECTORY) && !matches!(fs.entries[inode as usize], FsEntry::Dir(_))
-    {
+    if oflags.contains(OFlags::DIRECTORY) && !matches!(fs.entry(inode), FsEntry::Dir(_)) {
         return Errno::NotDir;
     };
-    let file_entry = match &mut fs.entries[inode as usize] {
+    let file_entry = match fs.entry(inode) {
+        FsEntry::Dir(_) if oflags.contains(OFlags::TRUNC) => return Errno::IsDir,
         FsEntry::Dir(_) => FdEntry::Dir(inode),
-        FsEntry::File(data) => {
-            if oflags.contains(OFlags::TRUNC) {
-                *data = Rc::new(Vec::new());
+        FsEntry::File(_) => {
+            if rights_base.contains(Rights::FD_WRITE) && fs.is_read_only(inode) {
+                return Errno::Rofs;
+            }
+            if oflags.contains(OFlags::TRUNC)
+                && let Err(e) = fs.set_file_size(inode, 0)
+            {
+                return e.into();
             }
             FdEntry::File(inode, 0, fd_flags.contains(FdFlags::APPEND))
         }
         FsEntry::Pipe(p) => FdEntry::Pipe(p.clone()),
+        FsEntry::Device(Device::Stdin) => match fds.first() {
+            Some(Some(stdin)) => stdin.entry.duplicate(),
+            _ => return Errno::NoEnt,
+        },
+        FsEntry::Device(device) => FdEntry::Device(*device),
+        FsEntry::Symlink(_) => return Errno::Loop,
     };
-    let fd = proc_inner.add_fd(file_entry);
+    // The new descriptor gets the requested rights that apply to it and that the directory
+    // allows.
+    let (max_rights, max_rights_inheriting) = Rights::for_entry(&file_entry);
+    let fd = proc_inner.add_fd(Descriptor {
+        entry: file_entry,
+        rights: rights_base & max_rights & dir_rights_inheriting,
+        rights_inheriting: rights_inheriting & max_rights_inheriting & dir_rights_inheriting,
+    });
     if let Err(e) = write_to_mem(proc, out, &fd) {
         return e;
     }
@@ -1157,72 +1614,131 @@ fn path_open(
 }
 
 fn path_readlink(
-    _proc: &Process,
-    _fd: Fd,
-    _path: Addr,
-    _path_len: Size,
-    _buf: Addr,
-    _buf_len: Size,
-    _out: Addr,
+    proc: &Process,
+    fd: Fd,
+    path_addr: Addr,
+    path_len: Size,
+    buf: Addr,
+    buf_len: Size,
+    out: Addr,
 ) -> Errno {
-    Errno::Perm
+    let proc_inner = proc.inner.borrow();
+    let base_inode = match get_dir(&proc_inner.fds, fd, Rights::PATH_READLINK) {
+        Ok(inode) => inode,
+        Err(e) => return e,
+    };
+    let mut path = vec![0; path_len as usize];
+    if let Err(e) = read_from_mem(proc, path_addr, &mut path[..]) {
+        return e;
+    }
+    let target = match proc_inner.fs.read_link(base_inode, &path) {
+        Ok(target) => target,
+        Err(e) => return e.into(),
+    };
+    let len = target.len().min(buf_len as usize);
+    if let Err(e) = write_to_mem(proc, buf, &target[..len]) {
+        return e;
+    }
+    if let Err(e) = write_to_mem(proc, out, &(len as Size)) {
+        return e;
+    }
+    Errno::Success
 }
 
-fn path_remove_directory(_proc: &Process, _fd: Fd, _path: Addr, _path_len: Size) -> Errno {
-    Errno::Perm
+fn path_remove_directory(proc: &Process, fd: Fd, path_addr: Addr, path_len: Size) -> Errno {
+    let mut proc_inner = proc.inner.borrow_mut();
+    let base_inode = match get_dir(&proc_inner.fds, fd, Rights::PATH_REMOVE_DIRECTORY) {
+        Ok(inode) => inode,
+        Err(e) => return e,
+    };
+    let mut path = vec![0; path_len as usize];
+    if let Err(e) = read_from_mem(proc, path_addr, &mut path[..]) {
+        return e;
+    }
+    if let Err(e) = proc_inner.fs.remove_dir(base_inode, &path) {
+        return e.into();
+    }
+    proc_inner.release_orphans();
+    Errno::Success
 }
 
 fn path_rename(
-    _proc: &Process,
-    _fd: Fd,
-    _old_path: Addr,
-    _old_path_len: Size,
-    _new_fd: Fd,
-    _new_path: Addr,
-    _new_path_len: Size,
+    proc: &Process,
+    fd: Fd,
+    old_path_addr: Addr,
+    old_path_len: Size,
+    new_fd: Fd,
+    new_path_addr: Addr,
+    new_path_len: Size,
 ) -> Errno {
-    Errno::Perm
+    let mut proc_inner = proc.inner.borrow_mut();
+    let old_base = match get_dir(&proc_inner.fds, fd, Rights::PATH_RENAME_SOURCE) {
+        Ok(inode) => inode,
+        Err(e) => return e,
+    };
+    let new_base = match get_dir(&proc_inner.fds, new_fd, Rights::PATH_RENAME_TARGET) {
+        Ok(inode) => inode,
+        Err(e) => return e,
+    };
+    let mut old_path = vec![0; old_path_len as usize];
+    if let Err(e) = read_from_mem(proc, old_path_addr, &mut old_path[..]) {
+        return e;
+    }
+    let mut new_path = vec![0; new_path_len as usize];
+    if let Err(e) = read_from_mem(proc, new_path_addr, &mut new_path[..]) {
+        return e;
+    }
+    if let Err(e) = proc_inner
+        .fs
+        .rename(old_base, &old_path, new_base, &new_path)
+    {
+        return e.into();
+    }
+    proc_inner.release_orphans();
+    Errno::Success
 }
 
 fn path_symlink(
-    _proc: &Process,
-    _source: Addr,
-    _source_len: Size,
-    _fd: Fd,
-    _path: Addr,
-    _path_len: Size,
+    proc: &Process,
+    target_addr: Addr,
+    target_len: Size,
+    fd: Fd,
+    path_addr: Addr,
+    path_len: Size,
 ) -> Errno {
-    Errno::Perm
+    let mut proc_inner = proc.inner.borrow_mut();
+    let base_inode = match get_dir(&proc_inner.fds, fd, Rights::PATH_SYMLINK) {
+        Ok(inode) => inode,
+        Err(e) => return e,
+    };
+    let mut target = vec![0; target_len as usize];
+    if let Err(e) = read_from_mem(proc, target_addr, &mut target[..]) {
+        return e;
+    }
+    let mut path = vec![0; path_len as usize];
+    if let Err(e) = read_from_mem(proc, path_addr, &mut path[..]) {
+        return e;
+    }
+    match proc_inner.fs.symlink(base_inode, &path, &target) {
+        Ok(_) => Errno::Success,
+        Err(e) => e.into(),
+    }
 }
 
 fn path_unlink_file(proc: &Process, fd: Fd, path_addr: Addr, path_len: Size) -> Errno {
-    // TODO: we are leaking files here
     let mut proc_inner = proc.inner.borrow_mut();
-    let Some(Some(file_entry)) = proc_inner.fds.get_mut(fd as usize) else {
-        return Errno::Badf;
-    };
-    let FdEntry::Dir(base_inode) = *file_entry else {
-        return Errno::Badf;
+    let base_inode = match get_dir(&proc_inner.fds, fd, Rights::PATH_UNLINK_FILE) {
+        Ok(inode) => inode,
+        Err(e) => return e,
     };
     let mut path = vec![0; path_len as usize];
     if let Err(e) = read_from_mem(proc, path_addr, &mut path[..]) {
         return e;
     }
-    let inode = match proc_inner.fs.get(base_inode, &path) {
-        Ok(inode) => inode,
-        Err(FsError::DoesNotExist) => return Errno::NoEnt,
-        Err(FsError::NotDir) => return Errno::NotDir,
-        Err(FsError::IsDir) => return Errno::IsDir,
-        Err(FsError::Exist) => return Errno::Exist,
-    };
-    if inode == proc_inner.fs.root() {
-        return Errno::Perm;
-    }
-    let parent = proc_inner.fs.parent_pointers[inode as usize];
-    proc_inner.fs.entries[parent as usize]
-        .as_dir_mut()
-        .unwrap()
-        .retain(|_, c| *c != inode);
+    if let Err(e) = proc_inner.fs.remove_file(base_inode, &path) {
+        return e.into();
+    }
+    proc_inner.release_orphans();
     Errno::Success
 }
 
@@ -1230,15 +1746,67 @@ fn proc_exit(proc: &Process, code: ExitCode) {
     proc.kill(StatusCode::Exited(code));
 }
 
-fn proc_raise(_proc: &Process, _code: Signal) -> Errno {
-    todo!()
+fn proc_raise(proc: &Process, signal: Signal) -> Errno {
+    match signal {
+        0 => Errno::Success,
+        // SIGCHLD, SIGCONT, SIGSTOP, SIGTSTP, SIGTTIN, SIGTTOU, SIGURG and SIGWINCH: there are
+        // neither signal handlers nor job control, so these have no effect.
+        16..=22 | 27 => Errno::Success,
+        _ if signal_name(signal).is_none() => Errno::Inval,
+        _ => {
+            proc.kill(StatusCode::Signaled(signal));
+            Errno::Success
+        }
+    }
+}
+
+/// Maximum number of bytes `crypto.getRandomValues` fills in a single call.
+const RANDOM_CHUNK_SIZE: Size = 65536;
+
+/// Fills `buf` with random bytes, generated by the PRNG in deterministic mode.
+fn fill_random(deterministic: &mut Option<Deterministic>, buf: &mut [u8]) {
+    if let Some(deterministic) = deterministic {
+        deterministic.fill_random(buf);
+        return;
+    }
+    let crypto = js_sys::global()
+        .unchecked_into::<WorkerGlobalScope>()
+        .crypto()
+        .expect("crypto should be available in workers");
+    let scratch = Uint8Array::new_with_length((buf.len() as Size).min(RANDOM_CHUNK_SIZE));
+    for chunk in buf.chunks_mut(RANDOM_CHUNK_SIZE as usize) {
+        let view = scratch.subarray(0, chunk.len() as Size);
+        crypto
+            .get_random_values_with_array_buffer_view(&view)
+            .expect("could not generate random values");
+        view.copy_to(chunk);
+    }
+}
+
+/// Reads from `device` into `buf`, returning the number of bytes read.
+fn read_device(device: Device, deterministic: &mut Option<Deterministic>, buf: &mut [u8]) -> usize {
+    match device {
+        Device::Null => 0,
+        Device::Zero => {
+            buf.fill(0);
+            buf.len()
+        }
+        Device::Random => {
+            fill_random(deterministic, buf);
+            buf.len()
+        }
+        Device::Stdin => unreachable!("/dev/stdin is opened as a copy of the standard input"),
+    }
 }
 
 fn random_get(proc: &Process, buf_addr: Addr, buf_len: Size) -> Errno {
-    let mut buf = vec![0u8; buf_len as usize];
-    for byte in buf.iter_mut() {
-        *byte = (js_sys::Math::random() * 256.) as u8;
+    if buf_addr as u64 + buf_len as u64 > Uint8Array::new(&proc.memory.buffer()).length() as u64 {
+        return Errno::Fault;
     }
+    // `getRandomValues` rejects views of shared memory, so the bytes are generated in a separate
+    // buffer and then copied into the process memory.
+    let mut buf = vec![0u8; buf_len as usize];
+    fill_random(&mut proc.inner.borrow_mut().deterministic, &mut buf);
     if let Err(e) = write_to_mem(proc, buf_addr, &buf[..]) {
         return e;
     }
@@ -1286,6 +1854,7 @@ fn sock_shutdown(proc: &Process, fd: Fd, _how: u8) -> Errno {
 
 async fn poll_oneoff(
     proc: &Process,
+    tid: u32,
     subs_addr: Addr,
     events_addr: Addr,
     num_subs: Size,
@@ -1327,15 +1896,25 @@ async fn poll_oneoff(
         .into_iter()
         .map(|sub| async move {
             if sub.event_type == 0 {
+                let clock_id = match sub.body[0] as u32 {
+                    0 => ClockId::Realtime,
+                    1 => ClockId::Monotonic,
+                    2 => ClockId::ProcessCpu,
+                    3 => ClockId::ThreadCpu,
+                    _ => {
+                        return Event {
+                            userdata: sub.userdata,
+                            error: Errno::Inval,
+                            event_type: sub.event_type,
+                            _pad: [0; 5],
+                            data: [0; 2],
+                        };
+                    }
+                };
                 let nanos = if sub.body[3] & 1 == 0 {
                     sub.body[1]
                 } else {
-                    let val = match sub.body[0] {
-                        0 /* ClockId::Realtime */ => web_time::UNIX_EPOCH.elapsed().unwrap().as_nanos() as Timestamp,
-                        1 /* ClockId::Monotonic */ => proc.start_instant.elapsed().as_nanos() as Timestamp,
-                        _ => unimplemented!(),
-                    };
-                    sub.body[1].saturating_sub(val)
+                    sub.body[1].saturating_sub(clock_now(proc, tid, clock_id))
                 };
                 TimeoutFuture::new(nanos.div_ceil(1_000_000).try_into().unwrap_or(u32::MAX)).await;
                 Event {
@@ -1345,8 +1924,20 @@ async fn poll_oneoff(
                     _pad: [0; 5],
                     data: [0; 2],
                 }
+            } else if sub.event_type == 1 || sub.event_type == 2 {
+                let fd = sub.body[0] as Fd;
+                let (error, data) = match fd_readiness(proc, fd, sub.event_type == 1).await {
+                    Ok((nbytes, hangup)) => (Errno::Success, [nbytes, hangup as u64]),
+                    Err(e) => (e, [0; 2]),
+                };
+                Event {
+                    userdata: sub.userdata,
+                    error,
+                    event_type: sub.event_type,
+                    _pad: [0; 5],
+                    data,
+                }
             } else {
-                // TODO(virv): support fd events
                 Event {
                     userdata: sub.userdata,
                     error: Errno::Inval,
@@ -1376,6 +1967,44 @@ async fn poll_oneoff(
     Errno::Success
 }
 
+/// Waits until `fd` is ready for reading (or writing, if `read` is false), and returns the
+/// number of bytes available and whether the other end was closed.
+async fn fd_readiness(proc: &Process, fd: Fd, read: bool) -> Result<(FileSize, bool), Errno> {
+    let pipe = {
+        let proc_inner = proc.inner.borrow();
+        let fd_entry = &get_fd(&proc_inner.fds, fd, Rights::POLL_FD_READWRITE)?.entry;
+        match (fd_entry, read) {
+            (FdEntry::Pipe(pipe), _) => pipe.clone(),
+            (FdEntry::Data { data, offset }, true) => {
+                return Ok((data.len().saturating_sub(*offset) as FileSize, false));
+            }
+            (FdEntry::File(inode, offset, _), true) => {
+                let data = proc_inner.fs.entry(*inode).as_file().unwrap();
+                return Ok((data.len().saturating_sub(*offset) as FileSize, false));
+            }
+            (FdEntry::Device(Device::Null), true) => return Ok((0, true)),
+            (FdEntry::Device(_), true) => return Ok((FileSize::MAX, false)),
+            (
+                FdEntry::Data { .. } | FdEntry::File(..) | FdEntry::WriteFn(_) | FdEntry::Device(_),
+                false,
+            ) => {
+                return Ok((0, false));
+            }
+            (FdEntry::WriteFn(_), true) | (FdEntry::Dir(_), _) => return Err(Errno::Badf),
+        }
+    };
+    if !read {
+        let nbytes = pipe.wait_writable().await;
+        return Ok((nbytes as FileSize, pipe.is_closed()));
+    }
+    let nbytes = pipe.wait_readable().await;
+    Ok((nbytes as FileSize, nbytes == 0 && pipe.is_closed()))
+}
+
 fn thread_spawn(proc: &Rc<Process>, attr: i32) -> i32 {
     proc.spawn_thread(Some(attr)) as i32
 }
+
+fn thread_exit(proc: &Process, tid: u32) {
+    proc.inner.borrow_mut().thread_times[tid as usize - 1].exit();
+}
@@ -0,0 +1,62 @@
+use std::cell::RefCell;
+
+use web_sys::{Worker, WorkerOptions, WorkerType};
+
+/// Number of idle thread workers kept ready for new threads.
+const POOL_SIZE: usize = 4;
+
+thread_local! {
+    /// Idle workers, with the name they were started with, oldest first.
+    static IDLE_WORKERS: RefCell<Vec<(String, Worker)>> = const { RefCell::new(Vec::new()) };
+}
+
+fn new_worker(name: &str) -> Worker {
+    let path = wasm_bindgen::link_to!(module = "/src/os/start_proc.js");
+    let options = WorkerOptions::default();
+    options.set_type(WorkerType::Module);
+    options.set_name(name);
+    Worker::new_with_options(&path, &options).expect("couldn't start thread")
+}
+
+/// Keeps at most `POOL_SIZE` idle workers, terminating the oldest ones.
+fn trim(idle: &mut Vec<(String, Worker)>) {
+    let excess = idle.len().saturating_sub(POOL_SIZE);
+    for (_, worker) in idle.drain(..excess) {
+        worker.terminate();
+    }
+}
+
+/// Starts idle thread workers with the given names, unless the pool already has them, so that
+/// they have loaded their script by the time they are needed.
+pub fn prewarm_threads<'a>(names: impl IntoIterator<Item = &'a str>) {
+    IDLE_WORKERS.with_borrow_mut(|idle| {
+        for name in names {
+            if !idle.iter().any(|(n, _)| n == name) {
+                idle.push((name.to_owned(), new_worker(name)));
+            }
+        }
+        trim(idle);
+    });
+}
+
+/// Takes an idle thread worker from the pool, or starts a new one if the pool has none. Workers
+/// are named when they start, so only the ones started with `name` are reused, which keeps the
+/// names shown by developer tools accurate.
+pub(super) fn acquire_worker(name: &str) -> Worker {
+    IDLE_WORKERS
+        .with_borrow_mut(|idle| {
+            let pos = idle.iter().position(|(n, _)| n == name)?;
+            Some(idle.remove(pos).1)
+        })
+        .unwrap_or_else(|| new_worker(name))
+}
+
+/// Returns a worker whose thread has finished running to the pool. Workers that are still
+/// running a thread cannot be reset, and must be terminated instead.
+pub(super) fn release_worker(name: String, worker: Worker) {
+    worker.set_onmessage(None);
+    IDLE_WORKERS.with_borrow_mut(|idle| {
+        idle.push((name, worker));
+        trim(idle);
+    });
+}
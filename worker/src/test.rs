@@ -1,12 +1,18 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use std::sync::Once;
 
-use common::init_logging;
+use common::{ExecConfig, IoMode, MAX_SEED, Trap, WorkerExecResponse, init_logging};
+use gloo_timers::future::TimeoutFuture;
+use js_sys::Uint8Array;
 use serde::Deserialize;
 use wasm_bindgen_test::*;
 
-use crate::os::{FdEntry, FsEntry, ProcessHandle, StatusCode};
+use crate::exec::random_seed;
+use crate::lang::solution_io;
+use crate::os::{
+    FdEntry, Fs, FsEntry, FsError, PIPE_CAPACITY, Pipe, ProcessHandle, StatusCode, Times,
+};
 use crate::util::fs_from_tar;
 
 wasm_bindgen_test_configure!(run_in_dedicated_worker);
@@ -37,7 +43,7 @@ async fn test(testsuite: &[u8]) {
     });
 
     let fs = fs_from_tar(testsuite).unwrap();
-    let FsEntry::Dir(root_dir) = &fs.entries[fs.root() as usize] else {
+    let FsEntry::Dir(root_dir) = fs.entry(fs.root()) else {
         panic!();
     };
 
@@ -57,6 +63,7 @@ async fn test(testsuite: &[u8]) {
             .map(|data| serde_json::from_slice::<Config>(&data).unwrap())
             .unwrap_or_default();
 
+        let code = fs.get_file_with_path(file).unwrap();
         let proc = ProcessHandle::builder()
             .fs(fs.clone())
             .stdin(FdEntry::Data {
@@ -80,7 +87,7 @@ async fn test(testsuite: &[u8]) {
             ._preopens(config.dirs.into_iter().map(String::into_bytes).collect())
             .arg(file.clone())
             .args(config.args)
-            .spawn_with_path(file);
+            .spawn_with_code(&code);
 
         let status_code = proc.proc.wait().await;
         assert_eq!(status_code, StatusCode::Exited(config.exit_code));
@@ -88,6 +95,7 @@ async fn test(testsuite: &[u8]) {
         let stdout = proc.proc.inner.borrow_mut().fds[1]
             .take()
             .unwrap()
+            .entry
             .into_data()
             .ok()
             .unwrap()
@@ -114,3 +122,986 @@ async fn test_c() {
 //    const TESTSUITE_RS: &[u8] = include_bytes!("../../testsuite/rs.tar");
 //    test(TESTSUITE_RS).await;
 //}
+
+/// Exit code of the modules built by [`checks_module`] when one of their checks fails.
+const CHECK_FAILED: u32 = 255;
+
+/// Address where [`checks_module`] records the check that failed: its id as an `i32`, then the
+/// value it got and the one it expected as `i64`s, at offsets 8 and 16.
+const CHECK_FAILURE_ADDR: u32 = 65504;
+
+/// Imports and helpers shared by the test modules. Scratch memory is laid out as follows: `$open`
+/// stores the new descriptor at 0, `$read` and `$write` store the number of bytes transferred at
+/// 8 and their iovec at 16.
+const CHECKS_PRELUDE: &str = r#"
+    (import "wasi_snapshot_preview1" "clock_time_get"
+        (func $clock_time_get (param i32 i64 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_close" (func $fd_close (param i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_fdstat_get"
+        (func $fd_fdstat_get (param i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_fdstat_set_rights"
+        (func $fd_fdstat_set_rights (param i32 i64 i64) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_filestat_get"
+        (func $fd_filestat_get (param i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_filestat_set_size"
+        (func $fd_filestat_set_size (param i32 i64) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_filestat_set_times"
+        (func $fd_filestat_set_times (param i32 i64 i64 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_pread"
+        (func $fd_pread (param i32 i32 i32 i64 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_prestat_get"
+        (func $fd_prestat_get (param i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_pwrite"
+        (func $fd_pwrite (param i32 i32 i32 i64 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_read"
+        (func $fd_read (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_renumber"
+        (func $fd_renumber (param i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_seek"
+        (func $fd_seek (param i32 i64 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write"
+        (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_filestat_get"
+        (func $path_filestat_get (param i32 i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_filestat_set_times"
+        (func $path_filestat_set_times (param i32 i32 i32 i32 i64 i64 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_open"
+        (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_remove_directory"
+        (func $path_remove_directory (param i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_unlink_file"
+        (func $path_unlink_file (param i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "poll_oneoff"
+        (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+    (import "wasi_snapshot_preview1" "proc_raise" (func $proc_raise (param i32) (result i32)))
+    (import "wasi_snapshot_preview1" "random_get"
+        (func $random_get (param i32 i32) (result i32)))
+    (import "env" "memory" (memory 1 1 shared))
+    ;; Records that check $id got $actual instead of $expected, and exits.
+    (func $fail (param $id i32) (param $actual i64) (param $expected i64)
+        (i32.store (i32.const 65504) (local.get $id))
+        (i64.store (i32.const 65512) (local.get $actual))
+        (i64.store (i32.const 65520) (local.get $expected))
+        (call $proc_exit (i32.const 255)))
+    (func $expect (param $id i32) (param $actual i32) (param $expected i32)
+        (if (i32.ne (local.get $actual) (local.get $expected))
+            (then (call $fail (local.get $id) (i64.extend_i32_s (local.get $actual))
+                (i64.extend_i32_s (local.get $expected))))))
+    (func $expect64 (param $id i32) (param $actual i64) (param $expected i64)
+        (if (i64.ne (local.get $actual) (local.get $expected))
+            (then (call $fail (local.get $id) (local.get $actual) (local.get $expected)))))
+    ;; Checks that a call returned no error.
+    (func $ok (param $id i32) (param $errno i32)
+        (call $expect (local.get $id) (local.get $errno) (i32.const 0)))
+    ;; Opens the path of length $len at $path, relative to the preopened root directory.
+    (func $open (param $path i32) (param $len i32) (param $oflags i32) (param $rights i64)
+        (result i32)
+        (call $path_open (i32.const 3) (i32.const 0) (local.get $path) (local.get $len)
+            (local.get $oflags) (local.get $rights) (i64.const 0) (i32.const 0) (i32.const 0)))
+    ;; The descriptor returned by the last `$open`.
+    (func $opened (result i32)
+        (i32.load (i32.const 0)))
+    (func $read (param $fd i32) (param $buf i32) (param $len i32) (result i32)
+        (i32.store (i32.const 16) (local.get $buf))
+        (i32.store (i32.const 20) (local.get $len))
+        (call $fd_read (local.get $fd) (i32.const 16) (i32.const 1) (i32.const 8)))
+    (func $write (param $fd i32) (param $buf i32) (param $len i32) (result i32)
+        (i32.store (i32.const 16) (local.get $buf))
+        (i32.store (i32.const 20) (local.get $len))
+        (call $fd_write (local.get $fd) (i32.const 16) (i32.const 1) (i32.const 8)))
+    ;; The number of bytes transferred by the last `$read` or `$write`.
+    (func $count (result i32)
+        (i32.load (i32.const 8)))
+"#;
+
+/// Returns a module made of [`CHECKS_PRELUDE`] followed by `items`, which must export `_start`.
+/// Its checks (`$expect`, `$expect64` and `$ok`) take an id, used to report which one failed.
+fn checks_module(items: &str) -> String {
+    format!("(module {CHECKS_PRELUDE} {items})")
+}
+
+fn empty_data() -> FdEntry {
+    FdEntry::Data {
+        data: Vec::new(),
+        offset: 0,
+    }
+}
+
+/// Takes the contents written to the in-memory file open as `fd`.
+fn take_data(proc: &ProcessHandle, fd: usize) -> Vec<u8> {
+    let descriptor = proc.proc.inner.borrow_mut().fds[fd].take().unwrap();
+    descriptor.entry.into_data().ok().unwrap().0
+}
+
+/// Runs a WebAssembly text module with in-memory stdout and stderr.
+async fn run_wat(wat: &str, fs: Fs, stdin: FdEntry) -> (ProcessHandle, StatusCode) {
+    let code = wat::parse_str(wat).unwrap();
+    let proc = ProcessHandle::builder()
+        .fs(fs)
+        .stdin(stdin)
+        .stdout(empty_data())
+        .stderr(empty_data())
+        .spawn_with_code(&code);
+    let status_code = proc.proc.wait().await;
+    (proc, status_code)
+}
+
+/// Panics with the check that failed if a module built by [`checks_module`] did not succeed.
+fn assert_checks_passed(proc: &ProcessHandle, status_code: StatusCode) {
+    if status_code == StatusCode::Exited(CHECK_FAILED) {
+        let memory = Uint8Array::new(&proc.proc.memory.buffer());
+        let record = memory
+            .subarray(CHECK_FAILURE_ADDR, CHECK_FAILURE_ADDR + 24)
+            .to_vec();
+        let id = u32::from_le_bytes(record[..4].try_into().unwrap());
+        let actual = i64::from_le_bytes(record[8..16].try_into().unwrap());
+        let expected = i64::from_le_bytes(record[16..].try_into().unwrap());
+        panic!("check {id} failed: got {actual}, expected {expected}");
+    }
+    assert_eq!(status_code, StatusCode::Exited(0));
+}
+
+/// Runs the checks in `items` (see [`checks_module`]) with an empty standard input.
+async fn run_checks(items: &str, fs: Fs) -> ProcessHandle {
+    run_checks_with_stdin(items, fs, empty_data()).await
+}
+
+async fn run_checks_with_stdin(items: &str, fs: Fs, stdin: FdEntry) -> ProcessHandle {
+    let (proc, status_code) = run_wat(&checks_module(items), fs, stdin).await;
+    assert_checks_passed(&proc, status_code);
+    proc
+}
+
+#[wasm_bindgen_test]
+async fn test_fd_renumber() {
+    // Moves stdout over stderr, then checks that fd 1 is closed and fd 2 writes to the
+    // former stdout.
+    let proc = run_checks(
+        r#"(data (i32.const 64) "hello")
+        (func (export "_start")
+            (call $ok (i32.const 1) (call $fd_renumber (i32.const 1) (i32.const 2)))
+            ;; EBADF
+            (call $expect (i32.const 2) (call $write (i32.const 1) (i32.const 64) (i32.const 5))
+                (i32.const 8))
+            (call $ok (i32.const 3) (call $write (i32.const 2) (i32.const 64) (i32.const 5))))"#,
+        Fs::new(),
+    )
+    .await;
+
+    assert!(proc.proc.inner.borrow().fds[1].is_none());
+    assert_eq!(take_data(&proc, 2), b"hello");
+}
+
+#[wasm_bindgen_test]
+async fn test_fd_renumber_errors() {
+    // Renumbering a descriptor to itself does nothing, and both descriptors must be open.
+    run_checks(
+        r#"(func (export "_start")
+            (call $ok (i32.const 1) (call $fd_renumber (i32.const 1) (i32.const 1)))
+            (call $expect (i32.const 2) (call $fd_renumber (i32.const 1) (i32.const 10))
+                (i32.const 8))
+            (call $expect (i32.const 3) (call $fd_renumber (i32.const 10) (i32.const 1))
+                (i32.const 8))
+            (call $expect (i32.const 4) (call $fd_renumber (i32.const 10) (i32.const 10))
+                (i32.const 8))
+            (call $ok (i32.const 5) (call $fd_renumber (i32.const 1) (i32.const 1))))"#,
+        Fs::new(),
+    )
+    .await;
+}
+
+#[wasm_bindgen_test]
+fn test_read_only() {
+    let mut fs = Fs::new();
+    let root = fs.root();
+    fs.add_file_with_path(b"ro/file", Rc::new(b"data".to_vec()));
+    fs.add_file_with_path(b"rw/file", Rc::new(b"data".to_vec()));
+    fs.set_read_only_with_path(b"ro").unwrap();
+    let file = fs.get(root, b"ro/file").unwrap();
+
+    // Existing entries can be opened, but nothing can be created, removed or changed.
+    assert_eq!(fs.open(root, b"ro/file", true, false, false).unwrap(), file);
+    for res in [
+        fs.open(root, b"ro/new", true, true, false),
+        fs.create_dir(root, b"ro/dir"),
+        fs.symlink(root, b"ro/link", b"file"),
+        fs.remove_file(root, b"ro/file"),
+    ] {
+        assert!(matches!(res, Err(FsError::ReadOnly)));
+    }
+    assert!(matches!(
+        fs.rename(root, b"ro/file", root, b"rw/moved"),
+        Err(FsError::ReadOnly)
+    ));
+    assert!(matches!(fs.set_file_size(file, 0), Err(FsError::ReadOnly)));
+    assert!(matches!(
+        fs.set_times(file, Some(0), None),
+        Err(FsError::ReadOnly)
+    ));
+    assert_eq!(*fs.get_file(file).unwrap(), b"data");
+
+    // The rest of the filesystem is unaffected.
+    fs.open(root, b"rw/new", true, true, false).unwrap();
+    fs.remove_file(root, b"rw/file").unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn test_open_read_only() {
+    let mut fs = Fs::new();
+    fs.add_file_with_path(b"ro/file", Rc::new(b"data".to_vec()));
+    fs.set_read_only_with_path(b"ro").unwrap();
+    run_checks(
+        r#"(data (i32.const 64) "ro/file")
+        (func (export "_start")
+            ;; Opening for writing fails with EROFS, opening for reading does not.
+            (call $expect (i32.const 1)
+                (call $open (i32.const 64) (i32.const 7) (i32.const 0) (i64.const 64))
+                (i32.const 69))
+            (call $ok (i32.const 2)
+                (call $open (i32.const 64) (i32.const 7) (i32.const 0) (i64.const 2)))
+            (call $expect (i32.const 3)
+                (call $path_unlink_file (i32.const 3) (i32.const 64) (i32.const 7))
+                (i32.const 69)))"#,
+        fs,
+    )
+    .await;
+}
+
+#[wasm_bindgen_test]
+fn test_fs_clone_is_isolated() {
+    let mut fs = Fs::new();
+    fs.add_file_with_path(b"dir/file", Rc::new(b"data".to_vec()));
+    fs.freeze();
+    let mut clone = fs.clone();
+    let root = clone.root();
+    let inode = clone.get(root, b"dir/file").unwrap();
+    clone.set_file_size(inode, 2).unwrap();
+    clone.rename(root, b"dir/file", root, b"moved").unwrap();
+    clone.add_file_with_path(b"dir/new", Rc::new(Vec::new()));
+
+    assert_eq!(*clone.get_file_with_path(b"moved").unwrap(), b"da");
+    assert_eq!(*fs.get_file_with_path(b"dir/file").unwrap(), b"data");
+    assert!(fs.get_file_with_path(b"moved").is_err());
+    assert!(fs.get_file_with_path(b"dir/new").is_err());
+}
+
+#[wasm_bindgen_test]
+async fn test_devices() {
+    run_checks(
+        r#"(data (i32.const 256) "dev/zero")
+        (data (i32.const 272) "dev/null")
+        (data (i32.const 288) "dev/urandom")
+        (func (export "_start")
+            (local $fd i32)
+            (i32.store (i32.const 32) (i32.const -1))
+            ;; /dev/zero reads as zeros.
+            (call $ok (i32.const 1)
+                (call $open (i32.const 256) (i32.const 8) (i32.const 0) (i64.const 66)))
+            (local.set $fd (call $opened))
+            (call $ok (i32.const 2) (call $read (local.get $fd) (i32.const 32) (i32.const 4)))
+            (call $expect (i32.const 3) (call $count) (i32.const 4))
+            (call $expect (i32.const 4) (i32.load (i32.const 32)) (i32.const 0))
+            ;; /dev/null reads as empty and discards writes.
+            (call $ok (i32.const 5)
+                (call $open (i32.const 272) (i32.const 8) (i32.const 0) (i64.const 66)))
+            (local.set $fd (call $opened))
+            (call $ok (i32.const 6) (call $read (local.get $fd) (i32.const 32) (i32.const 4)))
+            (call $expect (i32.const 7) (call $count) (i32.const 0))
+            (call $ok (i32.const 8) (call $write (local.get $fd) (i32.const 32) (i32.const 4)))
+            (call $expect (i32.const 9) (call $count) (i32.const 4))
+            ;; Devices are character devices.
+            (call $ok (i32.const 10) (call $path_filestat_get (i32.const 3) (i32.const 1)
+                (i32.const 288) (i32.const 11) (i32.const 64)))
+            (call $expect (i32.const 11) (i32.load8_u offset=16 (i32.const 64)) (i32.const 2)))"#,
+        Fs::new(),
+    )
+    .await;
+}
+
+#[wasm_bindgen_test]
+async fn test_pipe_backpressure() {
+    let pipe = Pipe::new();
+    let closed = Pipe::new();
+    closed.close();
+    let mut fs = Fs::new();
+    fs.add_entry_with_path(b"pipe", FsEntry::Pipe(pipe.clone()));
+    fs.add_entry_with_path(b"closed", FsEntry::Pipe(closed));
+    // Writes twice the pipe capacity, then writes to a closed pipe.
+    let run = run_checks(
+        r#"(data (i32.const 64) "pipe")
+        (data (i32.const 80) "closed")
+        (func (export "_start")
+            (call $ok (i32.const 1)
+                (call $open (i32.const 64) (i32.const 4) (i32.const 0) (i64.const 64)))
+            (i32.store (i32.const 32) (i32.const 0))
+            (i32.store (i32.const 36) (i32.const 65536))
+            (i32.store (i32.const 40) (i32.const 0))
+            (i32.store (i32.const 44) (i32.const 65536))
+            (call $ok (i32.const 2)
+                (call $fd_write (call $opened) (i32.const 32) (i32.const 2) (i32.const 8)))
+            (call $expect (i32.const 3) (call $count) (i32.const 131072))
+            (call $ok (i32.const 4)
+                (call $open (i32.const 80) (i32.const 6) (i32.const 0) (i64.const 64)))
+            ;; EPIPE
+            (call $expect (i32.const 5) (call $write (call $opened) (i32.const 0) (i32.const 1))
+                (i32.const 64)))"#,
+        fs,
+    );
+    let read = async {
+        let mut total = 0;
+        let mut buf = [0u8; 4096];
+        while total < 2 * PIPE_CAPACITY {
+            assert!(pipe.wait_readable().await <= PIPE_CAPACITY);
+            total += pipe.read(&mut buf).await;
+        }
+        total
+    };
+    let (_, total) = futures::join!(run, read);
+
+    assert_eq!(total, 2 * PIPE_CAPACITY);
+}
+
+#[wasm_bindgen_test]
+async fn test_late_kill_keeps_status() {
+    let code = wat::parse_str(r#"(module (func (export "_start")))"#).unwrap();
+    let proc = ProcessHandle::builder()
+        .stdin(empty_data())
+        .stdout(empty_data())
+        .stderr(empty_data())
+        .time_limit(Some(0.05))
+        .spawn_with_code(&code);
+    assert_eq!(proc.proc.wait().await, StatusCode::Exited(0));
+
+    // Neither the time limit expiring nor a cancellation replace the status it exited with.
+    TimeoutFuture::new(100).await;
+    proc.proc.kill(StatusCode::Cancelled);
+    assert_eq!(proc.proc.wait().await, StatusCode::Exited(0));
+}
+
+#[wasm_bindgen_test]
+async fn test_stdin_closed_on_exit() {
+    let stdin = Pipe::new();
+    // The process exits without reading, so the write can only end once stdin is closed.
+    let run = run_checks_with_stdin(
+        r#"(func (export "_start"))"#,
+        Fs::new(),
+        FdEntry::Pipe(stdin.clone()),
+    );
+    let input = vec![0; 2 * PIPE_CAPACITY];
+    let (_, res) = futures::join!(run, stdin.write(&input));
+
+    assert!(res.is_err());
+    assert!(stdin.is_closed());
+}
+
+#[wasm_bindgen_test]
+async fn test_fast_path() {
+    let stdin = Pipe::new();
+    stdin.write(b"hello world").await.unwrap();
+    stdin.close();
+    let proc = run_checks_with_stdin(
+        r#"(func $echo (param $len i32) (result i32)
+            ;; Reads at most $len bytes from stdin and writes them to stdout.
+            (call $ok (i32.const 100) (call $read (i32.const 0) (i32.const 64) (local.get $len)))
+            (call $ok (i32.const 101) (call $write (i32.const 1) (i32.const 64) (call $count)))
+            (call $count))
+        (func (export "_start")
+            ;; The first read goes through the host, which reads the rest ahead.
+            (call $expect (i32.const 1) (call $echo (i32.const 5)) (i32.const 5))
+            (call $expect (i32.const 2) (call $echo (i32.const 3)) (i32.const 3))
+            (call $expect (i32.const 3) (call $echo (i32.const 16)) (i32.const 3))
+            (call $expect (i32.const 4) (call $echo (i32.const 16)) (i32.const 0)))"#,
+        Fs::new(),
+        FdEntry::Pipe(stdin),
+    )
+    .await;
+
+    assert_eq!(take_data(&proc, 1), b"hello world");
+}
+
+#[wasm_bindgen_test]
+async fn test_read_after_truncate() {
+    let mut fs = Fs::new();
+    fs.add_file_with_path(b"file", Rc::new(b"hello".to_vec()));
+    // Truncates the file through one descriptor while another is positioned past the new end.
+    let proc = run_checks(
+        r#"(data (i32.const 64) "file")
+        (data (i32.const 80) "x")
+        (func (export "_start")
+            (local $writer i32)
+            (local $reader i32)
+            ;; FD_READ | FD_WRITE | FD_SEEK | FD_FILESTAT_SET_SIZE
+            (call $ok (i32.const 1)
+                (call $open (i32.const 64) (i32.const 4) (i32.const 0) (i64.const 4194374)))
+            (local.set $writer (call $opened))
+            (call $ok (i32.const 2)
+                (call $open (i32.const 64) (i32.const 4) (i32.const 0) (i64.const 4194374)))
+            (local.set $reader (call $opened))
+            (call $ok (i32.const 3) (call $read (local.get $reader) (i32.const 32) (i32.const 3)))
+            (call $ok (i32.const 4)
+                (call $fd_filestat_set_size (local.get $writer) (i64.const 0)))
+            ;; Reads past the end of the file read nothing.
+            (call $ok (i32.const 5) (call $read (local.get $reader) (i32.const 32) (i32.const 3)))
+            (call $expect (i32.const 6) (call $count) (i32.const 0))
+            (call $ok (i32.const 7) (call $fd_pread (local.get $reader) (i32.const 16)
+                (i32.const 1) (i64.const 10) (i32.const 8)))
+            (call $expect (i32.const 8) (call $count) (i32.const 0))
+            ;; Writes past the end of the file fill the gap with zeros.
+            (i32.store (i32.const 16) (i32.const 80))
+            (i32.store (i32.const 20) (i32.const 1))
+            (call $ok (i32.const 9) (call $fd_pwrite (local.get $writer) (i32.const 16)
+                (i32.const 1) (i64.const 4) (i32.const 8))))"#,
+        fs,
+    )
+    .await;
+
+    let inner = proc.proc.inner.borrow();
+    assert_eq!(*inner.fs.get_file_with_path(b"file").unwrap(), b"\0\0\0\0x");
+}
+
+#[wasm_bindgen_test]
+fn test_remove() {
+    let mut fs = Fs::new();
+    let root = fs.root();
+    fs.add_file_with_path(b"dir/file", Rc::new(Vec::new()));
+    assert!(matches!(
+        fs.remove_dir(root, b"dir"),
+        Err(FsError::NotEmpty)
+    ));
+    fs.remove_file(root, b"dir/file").unwrap();
+    fs.remove_dir(root, b"dir").unwrap();
+
+    // Removed inodes are reused, so repeatedly creating and removing a file does not grow the
+    // filesystem.
+    let mut inodes = HashSet::new();
+    for _ in 0..1000 {
+        inodes.insert(fs.add_entry_with_path(b"tmp", FsEntry::File(Rc::new(Vec::new()))));
+        fs.remove_file(root, b"tmp").unwrap();
+        fs.release_orphans(|_| false);
+    }
+    assert_eq!(inodes.len(), 1);
+}
+
+#[wasm_bindgen_test]
+async fn test_remove_open() {
+    let mut fs = Fs::new();
+    fs.add_file_with_path(b"file", Rc::new(b"data".to_vec()));
+    fs.add_dir_with_path(b"dir");
+    run_checks(
+        r#"(data (i32.const 64) "file")
+        (data (i32.const 80) "dir")
+        (func (export "_start")
+            (local $fd i32)
+            ;; A file that is still open can be read after it is unlinked.
+            (call $ok (i32.const 1)
+                (call $open (i32.const 64) (i32.const 4) (i32.const 0) (i64.const 2)))
+            (local.set $fd (call $opened))
+            (call $ok (i32.const 2)
+                (call $path_unlink_file (i32.const 3) (i32.const 64) (i32.const 4)))
+            ;; ENOENT
+            (call $expect (i32.const 3)
+                (call $open (i32.const 64) (i32.const 4) (i32.const 0) (i64.const 2))
+                (i32.const 44))
+            (call $ok (i32.const 4) (call $read (local.get $fd) (i32.const 32) (i32.const 8)))
+            (call $expect (i32.const 5) (call $count) (i32.const 4))
+            ;; A removed directory that is still open has no name.
+            (call $ok (i32.const 6)
+                (call $open (i32.const 80) (i32.const 3) (i32.const 2) (i64.const 2)))
+            (local.set $fd (call $opened))
+            (call $ok (i32.const 7)
+                (call $path_remove_directory (i32.const 3) (i32.const 80) (i32.const 3)))
+            (call $expect (i32.const 8) (call $fd_prestat_get (local.get $fd) (i32.const 32))
+                (i32.const 44)))"#,
+        fs,
+    )
+    .await;
+}
+
+#[wasm_bindgen_test]
+fn test_rename() {
+    let mut fs = Fs::new();
+    let root = fs.root();
+    fs.add_file_with_path(b"a", Rc::new(b"a".to_vec()));
+    fs.add_file_with_path(b"b", Rc::new(b"b".to_vec()));
+    fs.add_file_with_path(b"dir/file", Rc::new(Vec::new()));
+    fs.add_dir_with_path(b"empty");
+    fs.add_dir_with_path(b"full/sub");
+
+    // Files replace existing files.
+    fs.rename(root, b"a", root, b"b").unwrap();
+    assert!(fs.get_file_with_path(b"a").is_err());
+    assert_eq!(*fs.get_file_with_path(b"b").unwrap(), b"a");
+
+    // Renaming an entry to itself does nothing.
+    fs.rename(root, b"b", root, b"b").unwrap();
+    fs.rename(root, b"dir", root, b"./dir").unwrap();
+    assert_eq!(*fs.get_file_with_path(b"b").unwrap(), b"a");
+    assert!(fs.get_file_with_path(b"dir/file").is_ok());
+
+    // Directories only replace empty directories, and cannot move inside themselves.
+    assert!(matches!(
+        fs.rename(root, b"dir", root, b"full"),
+        Err(FsError::NotEmpty)
+    ));
+    assert!(matches!(
+        fs.rename(root, b"full", root, b"full/sub/inner"),
+        Err(FsError::Invalid)
+    ));
+    assert!(matches!(
+        fs.rename(root, b"full", root, b"full/inner"),
+        Err(FsError::Invalid)
+    ));
+    fs.rename(root, b"dir", root, b"empty").unwrap();
+    assert!(fs.get(root, b"dir").is_err());
+    assert!(fs.get_file_with_path(b"empty/file").is_ok());
+    assert!(fs.get(root, b"full/sub").is_ok());
+}
+
+#[wasm_bindgen_test]
+fn test_symlink_resolution() {
+    let mut fs = Fs::new();
+    let root = fs.root();
+    fs.add_file_with_path(b"dir/file", Rc::new(b"data".to_vec()));
+    fs.add_dir_with_path(b"dir/sub");
+    fs.add_file_with_path(b"other/file", Rc::new(Vec::new()));
+    let file = fs.get(root, b"dir/file").unwrap();
+    let dir = fs.get(root, b"dir").unwrap();
+    fs.symlink(root, b"dir/relative", b"file").unwrap();
+    fs.symlink(root, b"other/absolute", b"/dir/file").unwrap();
+    fs.symlink(root, b"to_sub", b"dir/sub").unwrap();
+    fs.symlink(root, b"loop_a", b"loop_b").unwrap();
+    fs.symlink(root, b"loop_b", b"loop_a").unwrap();
+    fs.symlink(root, b"dangling", b"dir/new").unwrap();
+
+    // Relative targets are resolved from the directory of the link, absolute ones from the root.
+    assert_eq!(fs.get(root, b"dir/relative").unwrap(), file);
+    assert_eq!(fs.get(root, b"other/absolute").unwrap(), file);
+    // `..` after a link goes to the parent of its target.
+    assert_eq!(fs.get(root, b"to_sub/..").unwrap(), dir);
+    assert_eq!(fs.get(root, b"to_sub/../file").unwrap(), file);
+    assert!(matches!(fs.get(root, b"loop_a"), Err(FsError::Loop)));
+    assert!(matches!(fs.get(root, b"loop_a/file"), Err(FsError::Loop)));
+    assert!(matches!(
+        fs.open(root, b"loop_a", true, true, false),
+        Err(FsError::Loop)
+    ));
+    // The last component is only followed if asked to.
+    let link = fs.lookup(root, b"dir/relative", false).unwrap();
+    assert_ne!(link, file);
+    assert_eq!(fs.read_link(root, b"dir/relative").unwrap(), b"file");
+    assert_eq!(
+        fs.open(root, b"dir/relative", false, false, false).unwrap(),
+        link
+    );
+    assert_eq!(
+        fs.open(root, b"dir/relative", true, false, false).unwrap(),
+        file
+    );
+
+    // Opening a dangling link only creates its target when asked to.
+    assert!(matches!(
+        fs.open(root, b"dangling", true, false, false),
+        Err(FsError::DoesNotExist)
+    ));
+    let new = fs.open(root, b"dangling", true, true, false).unwrap();
+    assert_eq!(fs.get(root, b"dir/new").unwrap(), new);
+}
+
+#[wasm_bindgen_test]
+async fn test_open_symlink() {
+    let mut fs = Fs::new();
+    let root = fs.root();
+    fs.add_file_with_path(b"file", Rc::new(b"data".to_vec()));
+    fs.symlink(root, b"link", b"file").unwrap();
+    run_checks(
+        r#"(data (i32.const 64) "link")
+        (func $open_link (param $dirflags i32) (result i32)
+            (call $path_open (i32.const 3) (local.get $dirflags) (i32.const 64) (i32.const 4)
+                (i32.const 0) (i64.const 66) (i64.const 0) (i32.const 0) (i32.const 0)))
+        (func (export "_start")
+            ;; Links are only opened without `SYMLINK_FOLLOW`, which fails with ELOOP.
+            (call $expect (i32.const 1) (call $open_link (i32.const 0)) (i32.const 32))
+            (call $ok (i32.const 2) (call $open_link (i32.const 1))))"#,
+        fs,
+    )
+    .await;
+}
+
+#[wasm_bindgen_test]
+async fn test_poll_pipe() {
+    let pipe = Pipe::new();
+    pipe.write(b"hello").await.unwrap();
+    let closed = Pipe::new();
+    closed.close();
+    let mut fs = Fs::new();
+    fs.add_entry_with_path(b"pipe", FsEntry::Pipe(pipe));
+    fs.add_entry_with_path(b"closed", FsEntry::Pipe(closed));
+    fs.add_entry_with_path(b"empty", FsEntry::Pipe(Pipe::new()));
+    run_checks(
+        r#"(data (i32.const 64) "pipe")
+        (data (i32.const 80) "closed")
+        (data (i32.const 96) "empty")
+        (func $open_pipe (param $path i32) (param $len i32) (result i32)
+            ;; FD_READ | FD_WRITE | POLL_FD_READWRITE
+            (call $ok (i32.const 100) (call $open (local.get $path) (local.get $len)
+                (i32.const 0) (i64.const 134217794)))
+            (call $opened))
+        ;; Writes a subscription at $addr for the fd (or clock) $fd, with $userdata as
+        ;; userdata. Clock subscriptions time out after 1ms.
+        (func $sub (param $addr i32) (param $userdata i64) (param $tag i32) (param $fd i32)
+            (memory.fill (local.get $addr) (i32.const 0) (i32.const 48))
+            (i64.store (local.get $addr) (local.get $userdata))
+            (i32.store8 offset=8 (local.get $addr) (local.get $tag))
+            (i32.store offset=16 (local.get $addr) (local.get $fd))
+            (i64.store offset=24 (local.get $addr) (i64.const 1000000)))
+        ;; Polls the subscriptions at 256 and checks that exactly one event, for $userdata,
+        ;; was returned at 512.
+        (func $poll (param $num i32) (param $userdata i64) (param $id i32)
+            (call $ok (local.get $id) (call $poll_oneoff (i32.const 256) (i32.const 512)
+                (local.get $num) (i32.const 8)))
+            (call $expect (i32.add (local.get $id) (i32.const 1)) (call $count) (i32.const 1))
+            (call $expect64 (i32.add (local.get $id) (i32.const 2)) (i64.load (i32.const 512))
+                (local.get $userdata))
+            (call $ok (i32.add (local.get $id) (i32.const 3))
+                (i32.load16_u offset=8 (i32.const 512))))
+        (func (export "_start")
+            (local $pipe i32)
+            (local.set $pipe (call $open_pipe (i32.const 64) (i32.const 4)))
+            ;; A pipe with data is readable, with the number of bytes buffered.
+            (call $sub (i32.const 256) (i64.const 7) (i32.const 1) (local.get $pipe))
+            (call $poll (i32.const 1) (i64.const 7) (i32.const 10))
+            (call $expect (i32.const 14) (i32.load8_u offset=10 (i32.const 512)) (i32.const 1))
+            (call $expect64 (i32.const 15) (i64.load offset=16 (i32.const 512)) (i64.const 5))
+            (call $expect (i32.const 16) (i32.load16_u offset=24 (i32.const 512)) (i32.const 0))
+            ;; It is writable, with the room left.
+            (call $sub (i32.const 256) (i64.const 8) (i32.const 2) (local.get $pipe))
+            (call $poll (i32.const 1) (i64.const 8) (i32.const 20))
+            (call $expect (i32.const 24) (i32.load8_u offset=10 (i32.const 512)) (i32.const 2))
+            (call $expect64 (i32.const 25) (i64.load offset=16 (i32.const 512))
+                (i64.const 65531))
+            ;; A closed, empty pipe is readable and hung up.
+            (call $sub (i32.const 256) (i64.const 9) (i32.const 1)
+                (call $open_pipe (i32.const 80) (i32.const 6)))
+            (call $poll (i32.const 1) (i64.const 9) (i32.const 30))
+            (call $expect64 (i32.const 34) (i64.load offset=16 (i32.const 512)) (i64.const 0))
+            (call $expect (i32.const 35) (i32.load16_u offset=24 (i32.const 512)) (i32.const 1))
+            ;; An open, empty pipe is not readable, so only the clock fires.
+            (call $sub (i32.const 256) (i64.const 10) (i32.const 1)
+                (call $open_pipe (i32.const 96) (i32.const 5)))
+            (call $sub (i32.const 304) (i64.const 11) (i32.const 0) (i32.const 1))
+            (call $poll (i32.const 2) (i64.const 11) (i32.const 40)))"#,
+        fs,
+    )
+    .await;
+}
+
+#[wasm_bindgen_test]
+async fn test_set_times() {
+    let mut fs = Fs::new();
+    let inode = fs.add_entry_with_path(b"file", FsEntry::File(Rc::new(Vec::new())));
+    *fs.times_mut(inode) = Times::at(0);
+    run_checks(
+        r#"(data (i32.const 64) "file")
+        ;; Checks that the access and modification times of $fd are either the given ones or,
+        ;; if -1, recent.
+        (func $check (param $fd i32) (param $atim i64) (param $mtim i64) (param $id i32)
+            (call $ok (local.get $id) (call $fd_filestat_get (local.get $fd) (i32.const 128)))
+            (if (i64.eq (local.get $atim) (i64.const -1))
+                (then (call $expect (i32.add (local.get $id) (i32.const 1))
+                    (i64.gt_u (i64.load offset=40 (i32.const 128))
+                        (i64.const 1000000000000000000))
+                    (i32.const 1)))
+                (else (call $expect64 (i32.add (local.get $id) (i32.const 1))
+                    (i64.load offset=40 (i32.const 128)) (local.get $atim))))
+            (if (i64.eq (local.get $mtim) (i64.const -1))
+                (then (call $expect (i32.add (local.get $id) (i32.const 2))
+                    (i64.gt_u (i64.load offset=48 (i32.const 128))
+                        (i64.const 1000000000000000000))
+                    (i32.const 1)))
+                (else (call $expect64 (i32.add (local.get $id) (i32.const 2))
+                    (i64.load offset=48 (i32.const 128)) (local.get $mtim)))))
+        (func (export "_start")
+            (local $fd i32)
+            ;; FD_WRITE | FD_FILESTAT_GET | FD_FILESTAT_SET_TIMES
+            (call $ok (i32.const 1)
+                (call $open (i32.const 64) (i32.const 4) (i32.const 0) (i64.const 10485824)))
+            (local.set $fd (call $opened))
+            (call $check (local.get $fd) (i64.const 0) (i64.const 0) (i32.const 10))
+            ;; ATIM | MTIM_NOW
+            (call $ok (i32.const 2) (call $path_filestat_set_times (i32.const 3) (i32.const 0)
+                (i32.const 64) (i32.const 4) (i64.const 5) (i64.const 0) (i32.const 9)))
+            (call $check (local.get $fd) (i64.const 5) (i64.const -1) (i32.const 20))
+            ;; ATIM_NOW | MTIM
+            (call $ok (i32.const 3) (call $fd_filestat_set_times (local.get $fd) (i64.const 0)
+                (i64.const 7) (i32.const 6)))
+            (call $check (local.get $fd) (i64.const -1) (i64.const 7) (i32.const 30))
+            ;; ATIM | ATIM_NOW is invalid.
+            (call $expect (i32.const 4) (call $fd_filestat_set_times (local.get $fd)
+                (i64.const 0) (i64.const 0) (i32.const 3)) (i32.const 28))
+            ;; Writing changes the modification time only.
+            (call $ok (i32.const 5) (call $fd_filestat_set_times (local.get $fd) (i64.const 0)
+                (i64.const 0) (i32.const 5)))
+            (call $ok (i32.const 6) (call $write (local.get $fd) (i32.const 64) (i32.const 4)))
+            (call $check (local.get $fd) (i64.const 0) (i64.const -1) (i32.const 40)))"#,
+        fs,
+    )
+    .await;
+}
+
+#[wasm_bindgen_test]
+async fn test_memory_limit() {
+    // Exits with an error after growing the memory fails.
+    let (_, status_code) = run_wat(
+        &checks_module(
+            r#"(func (export "_start")
+                (if (i32.eq (memory.grow (i32.const 1)) (i32.const -1))
+                    (then (call $proc_exit (i32.const 1)))))"#,
+        ),
+        Fs::new(),
+        empty_data(),
+    )
+    .await;
+    assert_eq!(status_code, StatusCode::MemoryLimitExceeded);
+
+    // Exits with an error while the memory is full, without failing to grow it.
+    let (_, status_code) = run_wat(
+        &checks_module(
+            r#"(func (export "_start")
+                (if (i32.eq (memory.grow (i32.const 0)) (i32.const 1))
+                    (then (call $proc_exit (i32.const 1)))))"#,
+        ),
+        Fs::new(),
+        empty_data(),
+    )
+    .await;
+    assert_eq!(status_code, StatusCode::Exited(1));
+}
+
+#[wasm_bindgen_test]
+fn test_seed_round_trip() {
+    // Seeds are exchanged with the frontend as JavaScript numbers, which must represent them
+    // exactly.
+    for seed in [random_seed(), MAX_SEED] {
+        let msg = serde_wasm_bindgen::to_value(&WorkerExecResponse::Seed(seed)).unwrap();
+        let msg = serde_wasm_bindgen::from_value(msg).unwrap();
+        assert!(matches!(msg, WorkerExecResponse::Seed(s) if s == seed));
+
+        let config = ExecConfig {
+            seed: Some(seed),
+            ..Default::default()
+        };
+        let config = serde_wasm_bindgen::to_value(&config).unwrap();
+        let config: ExecConfig = serde_wasm_bindgen::from_value(config).unwrap();
+        assert_eq!(config.seed, Some(seed));
+    }
+}
+
+#[wasm_bindgen_test]
+async fn test_deterministic() {
+    // Writes 16 random bytes, two readings of the realtime clock, and the modification times of
+    // a new file when created and when touched to stdout.
+    let code = wat::parse_str(checks_module(
+        r#"(data (i32.const 200) "new")
+        (func (export "_start")
+            (call $ok (i32.const 1) (call $random_get (i32.const 64) (i32.const 16)))
+            (call $ok (i32.const 2)
+                (call $clock_time_get (i32.const 0) (i64.const 1) (i32.const 80)))
+            (call $ok (i32.const 3)
+                (call $clock_time_get (i32.const 0) (i64.const 1) (i32.const 88)))
+            (call $ok (i32.const 4)
+                (call $open (i32.const 200) (i32.const 3) (i32.const 1) (i64.const 10485824)))
+            (call $ok (i32.const 5) (call $fd_filestat_get (call $opened) (i32.const 128)))
+            (i64.store (i32.const 96) (i64.load offset=48 (i32.const 128)))
+            ;; MTIM_NOW
+            (call $ok (i32.const 6) (call $fd_filestat_set_times (call $opened)
+                (i64.const 0) (i64.const 0) (i32.const 8)))
+            (call $ok (i32.const 7) (call $fd_filestat_get (call $opened) (i32.const 128)))
+            (i64.store (i32.const 104) (i64.load offset=48 (i32.const 128)))
+            (call $ok (i32.const 8) (call $write (i32.const 1) (i32.const 64) (i32.const 48))))"#,
+    ))
+    .unwrap();
+    async fn run(code: &[u8], seed: u64) -> Vec<u8> {
+        let proc = ProcessHandle::builder()
+            .stdin(empty_data())
+            .stdout(empty_data())
+            .stderr(empty_data())
+            .seed(Some(seed))
+            .spawn_with_code(code);
+        let status_code = proc.proc.wait().await;
+        assert_checks_passed(&proc, status_code);
+        take_data(&proc, 1)
+    }
+
+    let output = run(&code, 42).await;
+    assert_eq!(run(&code, 42).await, output);
+    assert_ne!(run(&code, 43).await[..16], output[..16]);
+    // File times come from the same virtual clock as the realtime clock.
+    let times: Vec<_> = output[16..]
+        .chunks(8)
+        .map(|x| u64::from_le_bytes(x.try_into().unwrap()))
+        .collect();
+    assert!(times.is_sorted_by(|a, b| a < b), "{times:?}");
+}
+
+#[wasm_bindgen_test]
+async fn test_rights() {
+    let mut fs = Fs::new();
+    fs.add_file_with_path(b"file", Rc::new(b"data".to_vec()));
+    let proc = run_checks(
+        r#"(data (i32.const 64) "file")
+        ;; Checks the rights of $fd, read at offset 8 of its fdstat.
+        (func $expect_rights (param $id i32) (param $fd i32) (param $rights i64)
+            (call $ok (local.get $id) (call $fd_fdstat_get (local.get $fd) (i32.const 128)))
+            (call $expect64 (i32.add (local.get $id) (i32.const 1))
+                (i64.load offset=8 (i32.const 128)) (local.get $rights)))
+        (func (export "_start")
+            (local $fd i32)
+            ;; FD_READ | FD_SEEK | FD_WRITE, and PATH_OPEN which does not apply to files.
+            (call $ok (i32.const 1)
+                (call $open (i32.const 64) (i32.const 4) (i32.const 0) (i64.const 8262)))
+            (local.set $fd (call $opened))
+            (call $expect_rights (i32.const 10) (local.get $fd) (i64.const 70))
+            ;; Without FD_WRITE, writes fail with ENOTCAPABLE while reads still work.
+            (call $ok (i32.const 2)
+                (call $fd_fdstat_set_rights (local.get $fd) (i64.const 6) (i64.const 0)))
+            (call $expect_rights (i32.const 20) (local.get $fd) (i64.const 6))
+            (call $expect (i32.const 3) (call $write (local.get $fd) (i32.const 64) (i32.const 4))
+                (i32.const 76))
+            (call $ok (i32.const 4) (call $read (local.get $fd) (i32.const 32) (i32.const 4)))
+            ;; Rights cannot be added back.
+            (call $expect (i32.const 5)
+                (call $fd_fdstat_set_rights (local.get $fd) (i64.const 70) (i64.const 0))
+                (i32.const 76))
+            ;; Seeking needs FD_SEEK, and reading the offset FD_TELL.
+            (call $ok (i32.const 6)
+                (call $fd_fdstat_set_rights (local.get $fd) (i64.const 2) (i64.const 0)))
+            (call $expect (i32.const 7) (call $fd_seek (local.get $fd) (i64.const 0)
+                (i32.const 0) (i32.const 32)) (i32.const 76))
+            (call $expect (i32.const 8) (call $fd_seek (local.get $fd) (i64.const 0)
+                (i32.const 1) (i32.const 32)) (i32.const 76))
+            ;; Writes to stdout fail too once it loses FD_WRITE.
+            (call $ok (i32.const 9) (call $write (i32.const 1) (i32.const 64) (i32.const 4)))
+            (call $ok (i32.const 30)
+                (call $fd_fdstat_set_rights (i32.const 1) (i64.const 0) (i64.const 0)))
+            (call $expect (i32.const 31) (call $write (i32.const 1) (i32.const 64) (i32.const 4))
+                (i32.const 76))
+            ;; Files opened from the root directory only get the rights it lets them inherit.
+            (call $ok (i32.const 40) (call $fd_fdstat_get (i32.const 3) (i32.const 128)))
+            (call $ok (i32.const 41) (call $fd_fdstat_set_rights (i32.const 3)
+                (i64.load offset=8 (i32.const 128)) (i64.const 2)))
+            (call $ok (i32.const 42)
+                (call $open (i32.const 64) (i32.const 4) (i32.const 0) (i64.const 70)))
+            (call $expect_rights (i32.const 50) (call $opened) (i64.const 2))
+            ;; Without PATH_OPEN, nothing can be opened.
+            (call $ok (i32.const 60)
+                (call $fd_fdstat_set_rights (i32.const 3) (i64.const 0) (i64.const 2)))
+            (call $expect (i32.const 61)
+                (call $open (i32.const 64) (i32.const 4) (i32.const 0) (i64.const 2))
+                (i32.const 76)))"#,
+        fs,
+    )
+    .await;
+
+    assert_eq!(take_data(&proc, 1), b"file");
+}
+
+#[wasm_bindgen_test]
+async fn test_io_file_names() {
+    let io_files = |input: &str, output: &str| IoMode::Files {
+        input: input.to_string(),
+        output: output.to_string(),
+    };
+    let mut fs = Fs::new();
+    fs.add_file_with_path(b"workdir/main.py", Rc::new(b"data".to_vec()));
+
+    for io_mode in [
+        io_files("", "output.txt"),
+        io_files("input.txt", "dir/output.txt"),
+        io_files("..", "output.txt"),
+        io_files("input.txt", "."),
+        io_files("file.txt", "file.txt"),
+        io_files("workdir", "output.txt"),
+        io_files("input.txt", "dev"),
+    ] {
+        let res = solution_io(&mut fs, &io_mode, Pipe::new(), Pipe::new()).await;
+        assert!(res.is_err(), "{io_mode:?} should be rejected");
+    }
+    let root = fs.root();
+    assert!(fs.get(root, b"input.txt").is_err());
+    assert!(fs.get(root, b"output.txt").is_err());
+
+    // The input is gathered into a regular file, while the output stays a pipe.
+    let stdin = Pipe::new();
+    stdin.write(b"input").await.unwrap();
+    stdin.close();
+    solution_io(
+        &mut fs,
+        &io_files("input.txt", "output.txt"),
+        stdin,
+        Pipe::new(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(*fs.get_file_with_path(b"input.txt").unwrap(), b"input");
+    assert!(matches!(
+        fs.entry(fs.get(root, b"output.txt").unwrap()),
+        FsEntry::Pipe(_)
+    ));
+}
+
+#[wasm_bindgen_test]
+fn test_from_trap() {
+    // Messages of V8, SpiderMonkey and JavaScriptCore.
+    let cases = [
+        ("unreachable", Trap::Unreachable),
+        ("unreachable executed", Trap::Unreachable),
+        (
+            "Unreachable code should not be executed.",
+            Trap::Unreachable,
+        ),
+        ("divide by zero", Trap::DivisionByZero),
+        ("remainder by zero", Trap::DivisionByZero),
+        ("integer divide by zero", Trap::DivisionByZero),
+        ("Division by zero", Trap::DivisionByZero),
+        ("divide result unrepresentable", Trap::IntegerOverflow),
+        ("integer overflow", Trap::IntegerOverflow),
+        ("Integer overflow", Trap::IntegerOverflow),
+        (
+            "float unrepresentable in integer range",
+            Trap::InvalidConversion,
+        ),
+        ("invalid conversion to integer", Trap::InvalidConversion),
+        ("Out of bounds Trunc operation", Trap::InvalidConversion),
+        ("memory access out of bounds", Trap::OutOfBounds),
+        ("index out of bounds", Trap::OutOfBounds),
+        ("Out of bounds memory access", Trap::OutOfBounds),
+        ("Maximum call stack size exceeded", Trap::StackOverflow),
+        ("too much recursion", Trap::StackOverflow),
+        ("Maximum call stack size exceeded.", Trap::StackOverflow),
+        (
+            "null function or function signature mismatch",
+            Trap::BadIndirectCall,
+        ),
+        ("table index is out of bounds", Trap::BadIndirectCall),
+        ("indirect call to null", Trap::BadIndirectCall),
+        ("indirect call signature mismatch", Trap::BadIndirectCall),
+        ("Out of bounds call_indirect", Trap::BadIndirectCall),
+        ("call_indirect to a null table entry", Trap::BadIndirectCall),
+        (
+            "call_indirect to a signature that does not match",
+            Trap::BadIndirectCall,
+        ),
+        (
+            "dereferencing a null pointer",
+            Trap::Other("dereferencing a null pointer".into()),
+        ),
+    ];
+    for (msg, trap) in cases {
+        assert_eq!(
+            StatusCode::from_trap(msg.into()),
+            StatusCode::Trapped(trap),
+            "{msg}"
+        );
+    }
+}
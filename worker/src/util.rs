@@ -1,27 +1,127 @@
+use std::collections::HashMap;
 use std::io::Read;
 use std::rc::Rc;
 
-use anyhow::{Context, Result, ensure};
+use anyhow::{Context, Result, anyhow, ensure};
+use common::config::{CompilerInfo, Config};
 use common::{WorkerExecResponse, WorkerExecStatus, WorkerResponse};
 use gloo_net::http::Request;
-use js_sys::{Reflect, Uint8Array};
-use tracing::{debug, info};
-use wasm_bindgen::JsCast;
+use js_sys::{Array, Reflect, Uint8Array};
+use tar::EntryType;
+use thiserror::Error;
+use tracing::{debug, info, warn};
+use wasm_bindgen::{JsCast, JsValue};
 use wasm_bindgen_futures::JsFuture;
-use web_sys::ReadableStreamDefaultReader;
+use web_sys::{Cache, ReadableStreamDefaultReader, Response, WorkerGlobalScope};
 
-use crate::os::Fs;
+use crate::os::{Fs, FsEntry, Times};
 use crate::{WORKER_STATE, send_msg};
 
-async fn fetch_tar(name: &str) -> Result<Vec<u8>> {
-    send_msg(WorkerResponse::FetchingCompiler(name.to_owned(), 0));
-    let url = format!("./compilers/{name}.tar");
-    let res = Request::get(&url).send().await?;
+pub fn js_error(e: JsValue) -> anyhow::Error {
+    anyhow!("{e:?}")
+}
+
+/// Name of the Cache API cache holding the compiler tarballs.
+const TAR_CACHE: &str = "compilers";
+
+fn tar_url(name: &str, version: &str) -> String {
+    format!("./compilers/{name}.tar?v={version}")
+}
+
+/// Fetches the information about the compiler tarballs from the manifest.
+async fn compiler_manifest() -> Result<HashMap<String, CompilerInfo>> {
+    let res = Request::get("./config.json").send().await?;
     ensure!(
         res.ok(),
-        "failed to fetch compiler tarball: HTTP {}",
+        "failed to fetch config.json: HTTP {}",
         res.status()
     );
+    let config: Config = res.json().await?;
+    Ok(config.compilers)
+}
+
+async fn open_tar_cache() -> Result<Cache> {
+    let caches = js_sys::global()
+        .unchecked_into::<WorkerGlobalScope>()
+        .caches()
+        .map_err(js_error)?;
+    let cache = JsFuture::from(caches.open(TAR_CACHE))
+        .await
+        .map_err(js_error)?;
+    Ok(cache.unchecked_into())
+}
+
+/// Removes the cached tarballs that are not in the manifest anymore.
+async fn evict_stale_tars(cache: &Cache, manifest: &HashMap<String, CompilerInfo>) -> Result<()> {
+    let current = manifest
+        .iter()
+        .map(|(name, info)| {
+            tar_url(name, &info.version)
+                .trim_start_matches('.')
+                .to_owned()
+        })
+        .collect::<Vec<_>>();
+    let keys = JsFuture::from(cache.keys()).await.map_err(js_error)?;
+    for request in keys.unchecked_into::<Array>() {
+        let request = request.unchecked_into::<web_sys::Request>();
+        let url = request.url();
+        if !current.iter().any(|suffix| url.ends_with(suffix)) {
+            info!("Evicting stale compiler tarball {url}");
+            JsFuture::from(cache.delete_with_request(&request))
+                .await
+                .map_err(js_error)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the response for the given tarball from the cache, if present.
+async fn cached_tar(cache: &Cache, url: &str) -> Result<Option<Response>> {
+    let res = JsFuture::from(cache.match_with_str(url))
+        .await
+        .map_err(js_error)?;
+    Ok(res.dyn_into().ok())
+}
+
+#[derive(Debug, Error)]
+#[error("compiler tarball is corrupted or incomplete (expected SHA-256 {expected}, got {actual})")]
+struct IntegrityError {
+    expected: String,
+    actual: String,
+}
+
+/// Returns the SHA-256 of `data`, as lowercase hex.
+pub async fn sha256(data: &[u8]) -> Result<String> {
+    let subtle = js_sys::global()
+        .unchecked_into::<WorkerGlobalScope>()
+        .crypto()
+        .map_err(js_error)?
+        .subtle();
+    let promise = subtle
+        .digest_with_str_and_u8_array("SHA-256", data)
+        .map_err(js_error)?;
+    let digest = JsFuture::from(promise).await.map_err(js_error)?;
+    Ok(Uint8Array::new(&digest)
+        .to_vec()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect())
+}
+
+/// Checks the tarball against the SHA-256 from the manifest.
+async fn verify_tar(body: &[u8], info: &CompilerInfo) -> Result<()> {
+    let actual = sha256(body).await?;
+    if actual != info.sha256 {
+        return Err(IntegrityError {
+            expected: info.sha256.clone(),
+            actual,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+async fn read_tar(name: &str, res: Response) -> Result<Vec<u8>> {
     let readable = res.body().context("missing body")?;
     let reader = readable
         .get_reader()
@@ -32,7 +132,8 @@ async fn fetch_tar(name: &str) -> Result<Vec<u8>> {
     loop {
         let data = JsFuture::from(reader.read())
             .await
-            .expect("failed to read from stream");
+            .map_err(js_error)
+            .context("failed to read compiler tarball")?;
 
         let done = Reflect::get(&data, &"done".into()).expect("failed to get done");
         let done = done.as_bool().expect("done is not a bool");
@@ -48,11 +149,58 @@ async fn fetch_tar(name: &str) -> Result<Vec<u8>> {
             body.len() as u64,
         ));
     }
+    Ok(body)
+}
 
-    send_msg(WorkerResponse::CompilerFetchDone(name.to_owned()));
+async fn fetch_tar_inner(
+    name: &str,
+    cache: Option<&Cache>,
+    info: &CompilerInfo,
+) -> Result<Vec<u8>> {
+    let url = tar_url(name, &info.version);
+    let cached = match cache {
+        Some(cache) => cached_tar(cache, &url).await.unwrap_or_else(|e| {
+            warn!("Failed to look up {url} in the cache: {e:?}");
+            None
+        }),
+        None => None,
+    };
+    if let Some(res) = cached {
+        info!("Loading {url} from the cache");
+        let body = read_tar(name, res).await?;
+        match verify_tar(&body, info).await {
+            Ok(()) => return Ok(body),
+            Err(e) => warn!("Cached {url} is invalid, downloading it again: {e:?}"),
+        }
+    }
+
+    let res = Request::get(&url).send().await?;
+    ensure!(
+        res.ok(),
+        "failed to fetch compiler tarball: HTTP {}",
+        res.status()
+    );
+    let res = Response::from(res);
+    // The copy is only stored once the body has been verified.
+    let copy = res.clone().ok();
+    let body = read_tar(name, res).await?;
+    verify_tar(&body, info).await?;
+    if let (Some(cache), Some(copy)) = (cache, copy)
+        && let Err(e) = JsFuture::from(cache.put_with_str(&url, &copy)).await
+    {
+        warn!("Failed to store {url} in the cache: {e:?}");
+    }
     Ok(body)
 }
 
+/// Downloads a compiler tarball, or loads it from the cache, and verifies its integrity.
+async fn fetch_tar(name: &str, cache: Option<&Cache>, info: &CompilerInfo) -> Result<Vec<u8>> {
+    send_msg(WorkerResponse::FetchingCompiler(name.to_owned(), 0));
+    let res = fetch_tar_inner(name, cache, info).await;
+    send_msg(WorkerResponse::CompilerFetchDone(name.to_owned()));
+    res
+}
+
 pub fn fs_from_tar(tar: &[u8]) -> Result<Fs> {
     let mut files = tar::Archive::new(tar);
     let mut fs = Fs::new();
@@ -66,21 +214,67 @@ pub fn fs_from_tar(tar: &[u8]) -> Result<Fs> {
             .strip_prefix(b".")
             .expect("invalid tarball")
             .to_vec();
-        let mut contents = vec![];
-        x.read_to_end(&mut contents)?;
-        fs.add_file_with_path(&path, Rc::new(contents));
+        let inode = match x.header().entry_type() {
+            EntryType::Directory => fs.add_dir_with_path(&path),
+            EntryType::Symlink => {
+                let target = x.link_name()?.context("symlink without target")?;
+                let target = target.to_string_lossy().as_bytes().to_vec();
+                fs.add_entry_with_path(&path, FsEntry::Symlink(target))
+            }
+            EntryType::Link => {
+                // Hard links are stored as absolute symlinks to the entry they point to.
+                let target = x.link_name()?.context("hard link without target")?;
+                let target = target.to_string_lossy();
+                let target = target.trim_start_matches("./").trim_start_matches('/');
+                let target = format!("/{target}").into_bytes();
+                fs.add_entry_with_path(&path, FsEntry::Symlink(target))
+            }
+            _ => {
+                let mut contents = vec![];
+                x.read_to_end(&mut contents)?;
+                fs.add_entry_with_path(&path, FsEntry::File(Rc::new(contents)))
+            }
+        };
+        if let Ok(mtime) = x.header().mtime() {
+            *fs.times_mut(inode) = Times::at(mtime * 1_000_000_000);
+        }
     }
+    fs.freeze();
     Ok(fs)
 }
 
+/// Toolchain directories that processes are not allowed to modify, if present in the tarball.
+const READ_ONLY_DIRS: &[&[u8]] = &[b"/bin", b"/include", b"/lib"];
+
 async fn get_fs_inner(name: &str) -> Result<Fs> {
     info!("Fetching {name}.tar");
-    let body = fetch_tar(name)
+    // The manifest is needed to verify the tarball. Without the Cache API, the tarball is
+    // downloaded every time.
+    let manifest = compiler_manifest()
+        .await
+        .context("Failed to load the compiler manifest")?;
+    let info = manifest
+        .get(name)
+        .with_context(|| format!("No compiler {name} in the manifest"))?;
+    let cache = open_tar_cache()
+        .await
+        .inspect_err(|e| warn!("Failed to open the compiler cache: {e:?}"))
+        .ok();
+    if let Some(cache) = &cache
+        && let Err(e) = evict_stale_tars(cache, &manifest).await
+    {
+        warn!("Failed to evict stale compiler tarballs: {e:?}");
+    }
+    let body = fetch_tar(name, cache.as_ref(), info)
         .await
         .with_context(|| format!("Failed to fetch compiler tarball for {name}"))?;
 
-    let fs =
+    let mut fs =
         fs_from_tar(&body).with_context(|| format!("Failed to deserialize tarball for {name}"))?;
+    for dir in READ_ONLY_DIRS {
+        // Missing directories are fine.
+        let _ = fs.set_read_only_with_path(dir);
+    }
     Ok(fs)
 }
 